@@ -0,0 +1,288 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bookkeeping of peer connections (as observed through the PeerMetadataStorage) to detect
+//! connection churn, e.g., caused by NAT or handshake problems.
+
+use crate::metrics;
+use aptos_config::network_id::{NetworkId, PeerNetworkId};
+use aptos_infallible::Mutex;
+use futures::stream::StreamExt;
+use network::{application::storage::PeerMetadataStorage, transport::ConnectionId};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_stream::wrappers::IntervalStream;
+
+const CONNECTION_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+const CHURN_RATE_WINDOW: Duration = Duration::from_secs(60);
+// The maximum number of peers whose reconnects are counted. On public networks, new peer
+// ids keep appearing, so the counts would otherwise grow without bound.
+const MAX_TRACKED_RECONNECT_PEERS: usize = 1_000;
+
+/// A connection that was observed as active when sampling the peer metadata
+struct ObservedConnection {
+    connection_id: ConnectionId,
+    direction: String,
+    established_at: Instant,
+}
+
+#[derive(Default)]
+struct ChurnState {
+    active_connections: HashMap<PeerNetworkId, ObservedConnection>,
+    recent_drops: HashMap<NetworkId, VecDeque<Instant>>,
+    reconnect_counts: HashMap<PeerNetworkId, u64>,
+}
+
+impl ChurnState {
+    /// Counts a new connection of the given peer. If too many peers are tracked, the least
+    /// reconnected peer (preferably one that isn't connected) is no longer tracked.
+    fn record_connection(&mut self, peer: PeerNetworkId) {
+        if let Some(reconnects) = self.reconnect_counts.get_mut(&peer) {
+            *reconnects += 1;
+            return;
+        }
+
+        if self.reconnect_counts.len() >= MAX_TRACKED_RECONNECT_PEERS {
+            let active_connections = &self.active_connections;
+            let evicted_peer = self
+                .reconnect_counts
+                .iter()
+                .min_by_key(|(peer, reconnects)| {
+                    (active_connections.contains_key(*peer), **reconnects)
+                })
+                .map(|(peer, _)| *peer);
+            if let Some(evicted_peer) = evicted_peer {
+                self.reconnect_counts.remove(&evicted_peer);
+            }
+        }
+        self.reconnect_counts.insert(peer, 0);
+    }
+}
+
+/// Periodically samples the connected peers of every network and maintains connection
+/// establishment, drop, lifetime and churn rate metrics.
+#[derive(Clone)]
+pub struct ConnectionChurnMonitor {
+    peer_metadata_storage: Arc<PeerMetadataStorage>,
+    state: Arc<Mutex<ChurnState>>,
+}
+
+impl ConnectionChurnMonitor {
+    pub fn new(peer_metadata_storage: Arc<PeerMetadataStorage>) -> Self {
+        Self {
+            peer_metadata_storage,
+            state: Arc::new(Mutex::new(ChurnState::default())),
+        }
+    }
+
+    pub async fn start(self) {
+        let mut sample_interval =
+            IntervalStream::new(tokio::time::interval(CONNECTION_SAMPLE_INTERVAL));
+        while sample_interval.next().await.is_some() {
+            self.sample_connections();
+        }
+    }
+
+    /// Returns the (at most) `count` peers that have reconnected most often
+    pub fn most_reconnected_peers(&self, count: usize) -> Vec<(PeerNetworkId, u64)> {
+        let mut reconnect_counts: Vec<_> = self
+            .state
+            .lock()
+            .reconnect_counts
+            .iter()
+            .map(|(peer, reconnects)| (*peer, *reconnects))
+            .collect();
+        reconnect_counts.sort_by(|(_, reconnects), (_, other)| other.cmp(reconnects));
+        reconnect_counts.truncate(count);
+        reconnect_counts
+    }
+
+    fn sample_connections(&self) {
+        self.sample_connections_at(Instant::now());
+    }
+
+    fn sample_connections_at(&self, now: Instant) {
+        let mut state = self.state.lock();
+
+        for network_id in self.peer_metadata_storage.networks() {
+            let connected_peers = self
+                .peer_metadata_storage
+                .read_filtered(network_id, |(_, peer_info)| peer_info.is_connected());
+
+            // Identify dropped connections (including peers that reconnected since the last sample)
+            let dropped_peers: Vec<_> = state
+                .active_connections
+                .iter()
+                .filter(|(peer, connection)| {
+                    peer.network_id() == network_id
+                        && connected_peers.get(peer).map_or(true, |peer_info| {
+                            peer_info.active_connection.connection_id != connection.connection_id
+                        })
+                })
+                .map(|(peer, _)| *peer)
+                .collect();
+            for peer in dropped_peers {
+                if let Some(connection) = state.active_connections.remove(&peer) {
+                    let network_label = network_id.to_string();
+                    metrics::CONNECTIONS_DROPPED
+                        .with_label_values(&[&network_label, &connection.direction])
+                        .inc();
+                    metrics::CONNECTION_LIFETIME_SECONDS
+                        .with_label_values(&[&network_label])
+                        .observe(connection.established_at.elapsed().as_secs_f64());
+                    state
+                        .recent_drops
+                        .entry(network_id)
+                        .or_default()
+                        .push_back(now);
+                }
+            }
+
+            // Identify newly established connections
+            for (peer, peer_info) in connected_peers {
+                if state.active_connections.contains_key(&peer) {
+                    continue;
+                }
+                let direction = peer_info.active_connection.origin.to_string();
+                metrics::CONNECTIONS_ESTABLISHED
+                    .with_label_values(&[&network_id.to_string(), &direction])
+                    .inc();
+                state.record_connection(peer);
+                state.active_connections.insert(
+                    peer,
+                    ObservedConnection {
+                        connection_id: peer_info.active_connection.connection_id,
+                        direction,
+                        established_at: now,
+                    },
+                );
+            }
+
+            // Update the churn rate (drops over the last window)
+            let recent_drops = state.recent_drops.entry(network_id).or_default();
+            while recent_drops.front().map_or(false, |dropped_at| {
+                now.duration_since(*dropped_at) > CHURN_RATE_WINDOW
+            }) {
+                recent_drops.pop_front();
+            }
+            metrics::CONNECTION_CHURN_RATE
+                .with_label_values(&[&network_id.to_string()])
+                .set(recent_drops.len() as i64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_types::PeerId;
+    use network::transport::ConnectionMetadata;
+
+    fn connect(
+        peer_metadata_storage: &PeerMetadataStorage,
+        peer: PeerNetworkId,
+        connection_id: u32,
+    ) {
+        let mut connection_metadata = ConnectionMetadata::mock(peer.peer_id());
+        connection_metadata.connection_id = ConnectionId::from(connection_id);
+        peer_metadata_storage.insert_connection(peer.network_id(), connection_metadata);
+    }
+
+    fn recent_drops(monitor: &ConnectionChurnMonitor, network_id: NetworkId) -> usize {
+        monitor
+            .state
+            .lock()
+            .recent_drops
+            .get(&network_id)
+            .map_or(0, |recent_drops| recent_drops.len())
+    }
+
+    #[test]
+    fn test_disconnect_and_reconnect() {
+        let peer_metadata_storage = PeerMetadataStorage::new(&[NetworkId::Public]);
+        let monitor = ConnectionChurnMonitor::new(peer_metadata_storage.clone());
+        let peer = PeerNetworkId::new(NetworkId::Public, PeerId::random());
+        let now = Instant::now();
+
+        connect(&peer_metadata_storage, peer, 1);
+        monitor.sample_connections_at(now);
+        assert_eq!(monitor.most_reconnected_peers(3), vec![(peer, 0)]);
+        assert_eq!(recent_drops(&monitor, NetworkId::Public), 0);
+
+        // The peer disconnects, and then reconnects with a new connection
+        peer_metadata_storage.remove(&peer);
+        monitor.sample_connections_at(now);
+        assert_eq!(recent_drops(&monitor, NetworkId::Public), 1);
+        assert!(monitor.state.lock().active_connections.is_empty());
+
+        connect(&peer_metadata_storage, peer, 2);
+        monitor.sample_connections_at(now);
+        assert_eq!(monitor.most_reconnected_peers(3), vec![(peer, 1)]);
+        assert_eq!(recent_drops(&monitor, NetworkId::Public), 1);
+
+        // A reconnect between two samples is both a drop and a reconnect
+        connect(&peer_metadata_storage, peer, 3);
+        monitor.sample_connections_at(now);
+        assert_eq!(monitor.most_reconnected_peers(3), vec![(peer, 2)]);
+        assert_eq!(recent_drops(&monitor, NetworkId::Public), 2);
+    }
+
+    #[test]
+    fn test_churn_rate_window() {
+        let peer_metadata_storage = PeerMetadataStorage::new(&[NetworkId::Vfn]);
+        let monitor = ConnectionChurnMonitor::new(peer_metadata_storage.clone());
+        let peer = PeerNetworkId::new(NetworkId::Vfn, PeerId::random());
+        let now = Instant::now();
+
+        connect(&peer_metadata_storage, peer, 1);
+        monitor.sample_connections_at(now);
+        peer_metadata_storage.remove(&peer);
+        monitor.sample_connections_at(now);
+        assert_eq!(recent_drops(&monitor, NetworkId::Vfn), 1);
+
+        // Drops are counted for the length of the window only
+        monitor.sample_connections_at(now + CHURN_RATE_WINDOW);
+        assert_eq!(recent_drops(&monitor, NetworkId::Vfn), 1);
+        monitor.sample_connections_at(now + CHURN_RATE_WINDOW + Duration::from_secs(1));
+        assert_eq!(recent_drops(&monitor, NetworkId::Vfn), 0);
+    }
+
+    #[test]
+    fn test_most_reconnected_peers() {
+        let monitor = ConnectionChurnMonitor::new(PeerMetadataStorage::new(&[]));
+        let peers: Vec<_> = (0..5)
+            .map(|_| PeerNetworkId::new(NetworkId::Public, PeerId::random()))
+            .collect();
+        for (reconnects, peer) in [3, 0, 7, 1, 5].iter().zip(&peers) {
+            monitor
+                .state
+                .lock()
+                .reconnect_counts
+                .insert(*peer, *reconnects);
+        }
+
+        assert_eq!(
+            monitor.most_reconnected_peers(3),
+            vec![(peers[2], 7), (peers[4], 5), (peers[0], 3)]
+        );
+        assert_eq!(monitor.most_reconnected_peers(10).len(), 5);
+    }
+
+    #[test]
+    fn test_reconnect_counts_are_bounded() {
+        let mut state = ChurnState::default();
+        let reconnected_peer = PeerNetworkId::new(NetworkId::Public, PeerId::random());
+        state.record_connection(reconnected_peer);
+        state.record_connection(reconnected_peer);
+
+        for _ in 0..MAX_TRACKED_RECONNECT_PEERS {
+            state.record_connection(PeerNetworkId::new(NetworkId::Public, PeerId::random()));
+        }
+        assert_eq!(state.reconnect_counts.len(), MAX_TRACKED_RECONNECT_PEERS);
+        // The least reconnected peers are evicted first
+        assert_eq!(state.reconnect_counts.get(&reconnected_peer), Some(&1));
+    }
+}
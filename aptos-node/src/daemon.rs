@@ -0,0 +1,165 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for running the node as a daemon without a service manager: detaching from the
+//! terminal, redirecting stdio and maintaining a PID file.
+
+use anyhow::{bail, Context, Result};
+use daemonize::Daemonize;
+use nix::{errno::Errno, sys::signal::kill, unistd::Pid};
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Detaches the process from the terminal (fork and setsid), redirecting stdout and stderr
+/// to the given files (or to /dev/null if not given). The working directory is preserved,
+/// so that relative config paths keep working. This must be called before any threads
+/// (e.g., runtimes) are started.
+pub fn daemonize(stdout: Option<&Path>, stderr: Option<&Path>) -> Result<()> {
+    let mut daemonize = Daemonize::new().working_directory(
+        std::env::current_dir().context("Failed to read the current working directory")?,
+    );
+    if let Some(stdout) = stdout {
+        daemonize = daemonize.stdout(open_output_file(stdout)?);
+    }
+    if let Some(stderr) = stderr {
+        daemonize = daemonize.stderr(open_output_file(stderr)?);
+    }
+    daemonize.start().context("Failed to daemonize the node")
+}
+
+fn open_output_file(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {:?} for writing", path))
+}
+
+/// A PID file owned by this process. The file is removed when dropped.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Creates the PID file at the given path, containing the PID of this process. Fails
+    /// if the file exists and belongs to a live process. A stale file (whose process is no
+    /// longer running) is replaced.
+    pub fn create(path: &Path) -> Result<Self> {
+        check_pid_file(path)?;
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .with_context(|| format!("Failed to create the PID file {:?}", path))?;
+        writeln!(file, "{}", std::process::id())
+            .with_context(|| format!("Failed to write the PID file {:?}", path))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Verifies that no live process owns the PID file at the given path, removing the file if
+/// it is stale. Returns the PID recorded in the removed stale file (if any). Fails without
+/// touching the file if it doesn't hold a PID, as it's then unlikely to be a PID file.
+pub fn check_pid_file(path: &Path) -> Result<Option<i32>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to read the PID file {:?}", path))
+        }
+    };
+
+    let pid = match contents.trim().parse::<i32>() {
+        Ok(pid) => pid,
+        Err(_) => bail!(
+            "The file {:?} doesn't hold a PID, refusing to replace it. Is the PID file path correct?",
+            path
+        ),
+    };
+    if is_process_alive(pid) {
+        bail!(
+            "The PID file {:?} belongs to a running process ({}). Is another node already running?",
+            path,
+            pid
+        );
+    }
+
+    std::fs::remove_file(path)
+        .with_context(|| format!("Failed to remove the stale PID file {:?}", path))?;
+    Ok(Some(pid))
+}
+
+fn is_process_alive(pid: i32) -> bool {
+    // Signal 0 only checks whether the process exists (EPERM means it exists, but belongs
+    // to another user)
+    match kill(Pid::from_raw(pid), None) {
+        Ok(()) => true,
+        Err(error) => error == Errno::EPERM,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_temppath::TempPath;
+
+    #[test]
+    fn test_pid_file_lifecycle() {
+        let path = TempPath::new();
+        let pid_file = PidFile::create(path.path()).unwrap();
+        let contents = std::fs::read_to_string(path.path()).unwrap();
+        assert_eq!(contents.trim(), std::process::id().to_string());
+
+        drop(pid_file);
+        assert!(!path.path().exists());
+    }
+
+    #[test]
+    fn test_live_pid_file_is_rejected() {
+        let path = TempPath::new();
+        let _pid_file = PidFile::create(path.path()).unwrap();
+        assert!(PidFile::create(path.path()).is_err());
+    }
+
+    #[test]
+    fn test_stale_pid_file_is_replaced() {
+        // Write the PID of a process that has already exited
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let stale_pid = child.id();
+        child.wait().unwrap();
+        let path = TempPath::new();
+        std::fs::write(path.path(), format!("{}\n", stale_pid)).unwrap();
+
+        assert_eq!(check_pid_file(path.path()).unwrap(), Some(stale_pid as i32));
+        assert!(!path.path().exists());
+
+        let _pid_file = PidFile::create(path.path()).unwrap();
+        let contents = std::fs::read_to_string(path.path()).unwrap();
+        assert_eq!(contents.trim(), std::process::id().to_string());
+    }
+
+    #[test]
+    fn test_non_pid_file_is_kept() {
+        // E.g., a mistyped --pid-file pointing at a config file
+        let path = TempPath::new();
+        std::fs::write(path.path(), "base:\n  role: validator\n").unwrap();
+
+        assert!(PidFile::create(path.path()).is_err());
+        let contents = std::fs::read_to_string(path.path()).unwrap();
+        assert_eq!(contents, "base:\n  role: validator\n");
+    }
+}
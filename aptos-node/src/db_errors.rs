@@ -0,0 +1,163 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Operator-facing diagnoses of the common reasons the DB fails to open, so that a lock
+//! conflict or a full disk doesn't surface as an opaque rocksdb error.
+
+use std::path::Path;
+
+/// The common causes of a failure to open the DB
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DbOpenErrorKind {
+    /// Another process (or another DB instance in this process) holds the DB lock
+    LockHeld,
+    /// The DB's CURRENT or MANIFEST file is missing
+    MissingFiles,
+    PermissionDenied,
+    DiskFull,
+    /// Rocksdb detected corrupted files (e.g., a damaged SST)
+    Corruption,
+    Unknown,
+}
+
+impl DbOpenErrorKind {
+    /// Classifies a DB open error by its (rocksdb) error message
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        if error.contains("no space left on device") {
+            DbOpenErrorKind::DiskFull
+        } else if error.contains("permission denied") {
+            DbOpenErrorKind::PermissionDenied
+        } else if error.contains("lock file")
+            || error.contains("lock hold by current process")
+            || error.contains("resource temporarily unavailable")
+        {
+            DbOpenErrorKind::LockHeld
+        } else if (error.contains("current") || error.contains("manifest"))
+            && (error.contains("does not exist") || error.contains("no such file"))
+        {
+            DbOpenErrorKind::MissingFiles
+        } else if error.contains("corruption") {
+            DbOpenErrorKind::Corruption
+        } else {
+            DbOpenErrorKind::Unknown
+        }
+    }
+
+    fn remediation(&self) -> &'static str {
+        match self {
+            DbOpenErrorKind::LockHeld => {
+                "Another process is using the DB. Stop it (or point this node at another \
+                storage directory) before restarting"
+            }
+            DbOpenErrorKind::MissingFiles => {
+                "The DB is incomplete. Restore it from a backup, or remove the directory to \
+                sync from scratch"
+            }
+            DbOpenErrorKind::PermissionDenied => {
+                "The node can't access the DB files. Make sure they're owned by (and writable \
+                for) the user running the node"
+            }
+            DbOpenErrorKind::DiskFull => {
+                "The disk is full. Free up space (or grow the volume) before restarting"
+            }
+            DbOpenErrorKind::Corruption => {
+                "The DB files are corrupted. Restore the DB from a backup, or remove the \
+                directory to sync from scratch"
+            }
+            DbOpenErrorKind::Unknown => "See the underlying error below",
+        }
+    }
+}
+
+/// Describes why the DB at `db_path` failed to open and how to fix it. For lock conflicts,
+/// the PID of the lock holder is included if the LOCK file records it.
+pub fn describe_db_open_error(db_path: &Path, error: &anyhow::Error) -> String {
+    let kind = DbOpenErrorKind::classify(&format!("{:#}", error));
+    let mut description = format!(
+        "Failed to open the DB at {:?} ({:?}). {}",
+        db_path,
+        kind,
+        kind.remediation()
+    );
+    if kind == DbOpenErrorKind::LockHeld {
+        if let Some(pid) = read_lock_holder_pid(db_path) {
+            description.push_str(&format!(" (the lock is held by PID {})", pid));
+        }
+    }
+    description
+}
+
+/// Returns the PID recorded in the DB's LOCK file. Rocksdb usually leaves the file empty,
+/// but some tooling writes the owner's PID into it.
+fn read_lock_holder_pid(db_path: &Path) -> Option<u32> {
+    std::fs::read_to_string(db_path.join("LOCK"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_temppath::TempPath;
+
+    #[test]
+    fn test_classify_db_open_errors() {
+        let errors = [
+            (
+                "IO error: While lock file: /opt/aptos/data/db/aptosdb/LOCK: Resource temporarily unavailable",
+                DbOpenErrorKind::LockHeld,
+            ),
+            (
+                "IO error: lock hold by current process, acquire time 1646000000 acquiring thread 1: /opt/aptos/data/db/aptosdb/LOCK: No locks available",
+                DbOpenErrorKind::LockHeld,
+            ),
+            (
+                "Invalid argument: /opt/aptos/data/db/aptosdb/CURRENT: does not exist (create_if_missing is false)",
+                DbOpenErrorKind::MissingFiles,
+            ),
+            (
+                "IO error: No such file or directory: While opening a file for sequentially reading: /opt/aptos/data/db/aptosdb/MANIFEST-000005: No such file or directory",
+                DbOpenErrorKind::MissingFiles,
+            ),
+            (
+                "IO error: While open a file for appending: /opt/aptos/data/db/aptosdb/000012.log: Permission denied",
+                DbOpenErrorKind::PermissionDenied,
+            ),
+            (
+                "IO error: No space left on device: While appending to file: /opt/aptos/data/db/aptosdb/000012.log: No space left on device",
+                DbOpenErrorKind::DiskFull,
+            ),
+            (
+                "Corruption: block checksum mismatch: stored = 1, computed = 2 in /opt/aptos/data/db/aptosdb/000042.sst",
+                DbOpenErrorKind::Corruption,
+            ),
+            ("Something unexpected", DbOpenErrorKind::Unknown),
+        ];
+        for (error, kind) in errors {
+            assert_eq!(DbOpenErrorKind::classify(error), kind, "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_lock_holder_pid() {
+        let db_dir = TempPath::new();
+        db_dir.create_as_dir().unwrap();
+        let error = anyhow::anyhow!(
+            "IO error: While lock file: {:?}: Resource temporarily unavailable",
+            db_dir.path().join("LOCK")
+        );
+
+        // An empty LOCK file doesn't identify its holder
+        std::fs::write(db_dir.path().join("LOCK"), "").unwrap();
+        let description = describe_db_open_error(db_dir.path(), &error);
+        assert!(description.contains("LockHeld"));
+        assert!(!description.contains("PID"));
+
+        std::fs::write(db_dir.path().join("LOCK"), "1234\n").unwrap();
+        let description = describe_db_open_error(db_dir.path(), &error);
+        assert!(description.contains("held by PID 1234"));
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Consistency checks of the node's storage, e.g., after restoring from a backup or after
+//! an unclean shutdown.
+
+use anyhow::{anyhow, bail, Context, Result};
+use aptos_config::config::NodeConfig;
+use aptos_logger::prelude::*;
+use aptos_state_view::account_with_state_view::AsAccountWithStateView;
+use aptos_types::{
+    account_config::aptos_root_address, account_view::AccountView, waypoint::Waypoint,
+};
+use aptosdb::AptosDB;
+use std::{str::FromStr, sync::Arc, time::Instant};
+use storage_interface::{state_view::DbStateViewAtVersion, DbReader};
+
+// The number of most recent versions re-verified in full mode
+const FULL_VERIFICATION_VERSIONS: u64 = 100_000;
+// The number of transactions fetched (and verified) at once in full mode
+const FULL_VERIFICATION_CHUNK_SIZE: u64 = 1_000;
+// The number of chunks verified between progress logs in full mode
+const FULL_VERIFICATION_PROGRESS_CHUNKS: u64 = 10;
+
+/// The thoroughness of the storage verification
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DbVerificationMode {
+    /// Verifies the latest ledger info against the transaction accumulator and checks that
+    /// the state at the latest version is readable.
+    Quick,
+    /// Performs the quick checks, and additionally re-verifies the transactions of the most
+    /// recent versions against the latest ledger info.
+    Full,
+}
+
+impl FromStr for DbVerificationMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "quick" => Ok(DbVerificationMode::Quick),
+            "full" => Ok(DbVerificationMode::Full),
+            _ => bail!(
+                "Invalid DB verification mode {}, expected quick or full",
+                mode
+            ),
+        }
+    }
+}
+
+/// Opens the DB of the given node config read-only and verifies its consistency. This
+/// allows the DB to be verified without starting the node.
+pub fn verify_node_db(node_config: &NodeConfig, mode: DbVerificationMode) -> Result<()> {
+    let db: Arc<dyn DbReader> = Arc::new(
+        AptosDB::open(
+            &node_config.storage.dir(),
+            true, /* readonly */
+            node_config.storage.storage_pruner_config,
+            node_config.storage.rocksdb_config,
+        )
+        .context("Failed to open the DB")?,
+    );
+    verify_db(&db, mode)
+}
+
+/// Verifies the consistency of the given DB, returning an error describing the first
+/// inconsistency found.
+pub fn verify_db(db: &Arc<dyn DbReader>, mode: DbVerificationMode) -> Result<()> {
+    let start_time = Instant::now();
+
+    // Verify the latest ledger info against the transaction accumulator
+    let ledger_info_with_sigs = db
+        .get_latest_ledger_info()
+        .context("Failed to read the latest ledger info")?;
+    let ledger_info = ledger_info_with_sigs.ledger_info();
+    let latest_version = ledger_info.version();
+    let expected_root_hash = ledger_info.transaction_accumulator_hash();
+    let actual_root_hash = db
+        .get_accumulator_root_hash(latest_version)
+        .with_context(|| format!("Failed to read the accumulator root at {}", latest_version))?;
+    if expected_root_hash != actual_root_hash {
+        bail!(
+            "Transaction accumulator mismatch at version {}! Expected root hash (from the \
+            latest ledger info): {}, actual root hash: {}",
+            latest_version,
+            expected_root_hash,
+            actual_root_hash
+        );
+    }
+
+    // Verify the state at the latest version is readable
+    db.state_view_at_version(Some(latest_version))
+        .and_then(|state_view| {
+            state_view
+                .as_account_with_state_view(&aptos_root_address())
+                .get_chain_id_resource()
+        })
+        .with_context(|| format!("Failed to read the state at version {}", latest_version))?
+        .ok_or_else(|| anyhow!("Missing chain ID resource at version {}", latest_version))?;
+
+    // Re-verify the most recent transactions against the latest ledger info
+    let mut num_verified_versions = 0;
+    if mode == DbVerificationMode::Full {
+        let first_version = (latest_version + 1).saturating_sub(FULL_VERIFICATION_VERSIONS);
+        info!(
+            "Verifying the transactions between versions {} and {}",
+            first_version, latest_version
+        );
+
+        let mut chunk_start_version = first_version;
+        let mut num_chunks = 0;
+        while chunk_start_version <= latest_version {
+            let chunk_size =
+                FULL_VERIFICATION_CHUNK_SIZE.min(latest_version - chunk_start_version + 1);
+            db.get_transactions(chunk_start_version, chunk_size, latest_version, false)
+                .and_then(|transactions| {
+                    transactions.verify(ledger_info, Some(chunk_start_version))
+                })
+                .with_context(|| {
+                    format!(
+                        "Failed to verify the transactions between versions {} and {}",
+                        chunk_start_version,
+                        chunk_start_version + chunk_size - 1
+                    )
+                })?;
+            chunk_start_version += chunk_size;
+            num_verified_versions += chunk_size;
+
+            num_chunks += 1;
+            if num_chunks % FULL_VERIFICATION_PROGRESS_CHUNKS == 0 {
+                info!(
+                    "Verified {} of {} versions",
+                    num_verified_versions,
+                    latest_version - first_version + 1
+                );
+            }
+        }
+    }
+
+    info!(
+        mode = format!("{:?}", mode),
+        latest_version = latest_version,
+        root_hash = expected_root_hash,
+        verified_versions = num_verified_versions,
+        elapsed_ms = start_time.elapsed().as_millis() as u64,
+        "DB verification succeeded"
+    );
+    Ok(())
+}
+
+/// Verifies that the DB doesn't contradict the given waypoint, i.e., that if the DB already
+/// contains the waypoint version, the epoch ending ledger info at that version matches the
+/// waypoint. A mismatch means the waypoint is for another network or the DB is corrupted,
+/// in which case state sync would reject everything it receives. An empty DB, or one that
+/// hasn't reached the waypoint yet, is consistent with any waypoint.
+pub fn check_waypoint_consistency(db: &Arc<dyn DbReader>, waypoint: Waypoint) -> Result<()> {
+    let latest_version = match db
+        .get_latest_ledger_info_option()
+        .context("Failed to read the latest ledger info")?
+    {
+        Some(ledger_info) => ledger_info.ledger_info().version(),
+        None => return Ok(()), // The DB is empty
+    };
+    if latest_version < waypoint.version() {
+        warn!(
+            latest_version = latest_version,
+            waypoint_version = waypoint.version(),
+            "The DB is at version {}, below the waypoint version {}. The waypoint can't be \
+            checked against the DB until the node has synced to it",
+            latest_version,
+            waypoint.version()
+        );
+        return Ok(());
+    }
+
+    let ledger_info = db
+        .get_epoch_ending_ledger_info(waypoint.version())
+        .with_context(|| {
+            format!(
+                "The waypoint {} doesn't match the DB: the DB contains version {}, but has no \
+                epoch ending ledger info at that version. The waypoint is likely for another \
+                network, or the DB is corrupted",
+                waypoint,
+                waypoint.version()
+            )
+        })?;
+    if let Err(error) = waypoint.verify(ledger_info.ledger_info()) {
+        let db_waypoint = Waypoint::new_epoch_boundary(ledger_info.ledger_info())?;
+        bail!(
+            "The waypoint doesn't match the DB at version {}! Configured waypoint: {}, \
+            DB waypoint: {}. The waypoint is likely for another network, or the DB is \
+            corrupted: {}",
+            waypoint.version(),
+            waypoint,
+            db_waypoint,
+            error
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_crypto::HashValue;
+    use aptos_genesis_tool::validator_builder::ValidatorBuilder;
+    use aptos_temppath::TempPath;
+    use aptos_types::{block_info::BlockInfo, ledger_info::LedgerInfo};
+    use aptos_vm::AptosVM;
+    use executor::db_bootstrapper::maybe_bootstrap;
+    use rand::{rngs::StdRng, SeedableRng};
+    use storage_interface::DbReaderWriter;
+
+    /// Builds a test genesis from the given seed and returns its waypoint, along with a DB
+    /// bootstrapped with it if `bootstrap` is set (an empty DB otherwise)
+    fn test_db(db_dir: &TempPath, seed: u8, bootstrap: bool) -> (Arc<dyn DbReader>, Waypoint) {
+        let config_dir = TempPath::new();
+        config_dir.create_as_dir().unwrap();
+        let (_, genesis, genesis_waypoint, _) = ValidatorBuilder::new(
+            config_dir.path(),
+            cached_framework_packages::module_blobs().to_vec(),
+        )
+        .build(StdRng::from_seed([seed; 32]))
+        .unwrap();
+
+        let db_rw = DbReaderWriter::new(AptosDB::new_for_test(db_dir));
+        if bootstrap {
+            maybe_bootstrap::<AptosVM>(&db_rw, &genesis, genesis_waypoint).unwrap();
+        }
+        (db_rw.reader, genesis_waypoint)
+    }
+
+    #[test]
+    fn test_verify_db() {
+        let db_dir = TempPath::new();
+        let (db, _) = test_db(&db_dir, 0, true);
+        verify_db(&db, DbVerificationMode::Quick).unwrap();
+        verify_db(&db, DbVerificationMode::Full).unwrap();
+    }
+
+    #[test]
+    fn test_verify_db_missing_ledger_info() {
+        let db_dir = TempPath::new();
+        let (db, _) = test_db(&db_dir, 0, false);
+        for mode in [DbVerificationMode::Quick, DbVerificationMode::Full] {
+            let error = verify_db(&db, mode).unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("Failed to read the latest ledger info"));
+        }
+    }
+
+    #[test]
+    fn test_waypoint_consistency_matching_waypoint() {
+        let db_dir = TempPath::new();
+        let (db, waypoint) = test_db(&db_dir, 0, true);
+        check_waypoint_consistency(&db, waypoint).unwrap();
+    }
+
+    #[test]
+    fn test_waypoint_consistency_other_genesis() {
+        let db_dir = TempPath::new();
+        let (db, _) = test_db(&db_dir, 0, true);
+        let other_db_dir = TempPath::new();
+        let (_, other_waypoint) = test_db(&other_db_dir, 1, false);
+
+        let error = check_waypoint_consistency(&db, other_waypoint).unwrap_err();
+        assert!(error.to_string().contains("doesn't match the DB"));
+    }
+
+    #[test]
+    fn test_waypoint_consistency_empty_db() {
+        let db_dir = TempPath::new();
+        let (db, waypoint) = test_db(&db_dir, 0, false);
+        check_waypoint_consistency(&db, waypoint).unwrap();
+    }
+
+    #[test]
+    fn test_waypoint_consistency_waypoint_ahead_of_db() {
+        let db_dir = TempPath::new();
+        let (db, _) = test_db(&db_dir, 0, true);
+        let block_info = BlockInfo::new(1, 0, HashValue::zero(), HashValue::zero(), 10, 0, None);
+        let waypoint = Waypoint::new_any(&LedgerInfo::new(block_info, HashValue::zero()));
+        check_waypoint_consistency(&db, waypoint).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Failpoint setup at startup, from the failpoints configured in the node config.
+
+use anyhow::{bail, Result};
+use aptos_logger::prelude::*;
+use std::collections::HashMap;
+
+/// Configures the given failpoints (if the binary is compiled with failpoint support).
+/// Every action string is parsed before returning, and all invalid entries are reported
+/// together with their failpoint names.
+pub fn setup_failpoints(
+    failpoints: Option<&HashMap<String, String>>,
+    failpoints_enabled: bool,
+) -> Result<()> {
+    let mut failpoints: Vec<_> = failpoints.into_iter().flatten().collect();
+    failpoints.sort();
+
+    if !failpoints_enabled {
+        if !failpoints.is_empty() {
+            let points: Vec<&str> = failpoints.iter().map(|(point, _)| point.as_str()).collect();
+            warn!(
+                "failpoints is set in config, but the binary doesn't compile with this feature. Ignoring failpoints: {}",
+                points.join(", ")
+            );
+        }
+        return Ok(());
+    }
+
+    warn!("Failpoints is enabled");
+    let invalid_failpoints: Vec<String> = failpoints
+        .into_iter()
+        .filter_map(|(point, actions)| {
+            fail::cfg(point, actions)
+                .err()
+                .map(|error| format!("{} ({:?}): {}", point, actions, error))
+        })
+        .collect();
+    if !invalid_failpoints.is_empty() {
+        bail!(
+            "Invalid failpoint actions: {}",
+            invalid_failpoints.join("; ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_valid_failpoints() {
+        let failpoints: HashMap<String, String> = vec![
+            ("test::valid_a".into(), "return".into()),
+            ("test::valid_b".into(), "10%return->off".into()),
+        ]
+        .into_iter()
+        .collect();
+        setup_failpoints(Some(&failpoints), true).unwrap();
+        setup_failpoints(None, true).unwrap();
+
+        for point in failpoints.keys() {
+            fail::remove(point);
+        }
+    }
+
+    #[test]
+    fn test_setup_invalid_failpoints() {
+        let failpoints: HashMap<String, String> = vec![
+            ("test::invalid_a".into(), "retrun".into()),
+            ("test::invalid_b".into(), "bogus(message)".into()),
+            ("test::valid".into(), "off".into()),
+        ]
+        .into_iter()
+        .collect();
+        let error = setup_failpoints(Some(&failpoints), true)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("test::invalid_a"));
+        assert!(error.contains("test::invalid_b"));
+        assert!(!error.contains("test::valid"));
+
+        for point in failpoints.keys() {
+            fail::remove(point);
+        }
+    }
+
+    #[test]
+    fn test_setup_failpoints_disabled() {
+        // Without failpoint support, the actions are neither configured nor validated
+        let failpoints: HashMap<String, String> = vec![("test::disabled".into(), "retrun".into())]
+            .into_iter()
+            .collect();
+        setup_failpoints(Some(&failpoints), false).unwrap();
+        assert!(fail::list()
+            .iter()
+            .all(|(point, _)| point != "test::disabled"));
+    }
+}
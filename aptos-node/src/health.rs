@@ -0,0 +1,207 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! A lightweight HTTP server exposing liveness (`/live`) and readiness (`/ready`) probes for
+//! orchestrators.
+
+use crate::node_context::NodeContext;
+use anyhow::{Context, Result};
+use aptos_logger::prelude::*;
+use aptos_types::{move_resource::MoveStorage, transaction::Version};
+use serde::Serialize;
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::runtime::Handle;
+use warp::{
+    http::StatusCode,
+    reply::{Reply, Response},
+    Filter,
+};
+
+/// The health of the node, as reported by both probes
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct HealthStatus {
+    pub ready: bool,
+    pub chain_id: u8,
+    pub synced_version: Option<Version>,
+    pub epoch: Option<u64>,
+    pub consensus_running: bool,
+}
+
+/// Tracks the startup progress of the node and derives its health from it
+pub struct HealthChecker {
+    node_context: Arc<NodeContext>,
+    require_state_sync_initialized: bool,
+    started: AtomicBool,
+    state_sync_initialized: AtomicBool,
+    consensus_running: AtomicBool,
+}
+
+impl HealthChecker {
+    /// Creates a health checker for a node that isn't started yet. If
+    /// `require_state_sync_initialized` is set, the node is only ready once state sync has
+    /// initialized as well.
+    pub fn new(node_context: Arc<NodeContext>, require_state_sync_initialized: bool) -> Arc<Self> {
+        Arc::new(Self {
+            node_context,
+            require_state_sync_initialized,
+            started: AtomicBool::new(false),
+            state_sync_initialized: AtomicBool::new(false),
+            consensus_running: AtomicBool::new(false),
+        })
+    }
+
+    /// Marks the node as started, i.e., all of its components (including the API) are up
+    pub fn set_started(&self, consensus_running: bool) {
+        self.consensus_running
+            .store(consensus_running, Ordering::Release);
+        self.started.store(true, Ordering::Release);
+    }
+
+    /// Marks state sync as initialized (or as not needing to wait, if the DB already
+    /// satisfies the waypoint)
+    pub fn set_state_sync_initialized(&self) {
+        self.state_sync_initialized.store(true, Ordering::Release);
+    }
+
+    /// Marks consensus as running (e.g., after a delayed consensus start)
+    pub fn set_consensus_running(&self) {
+        self.consensus_running.store(true, Ordering::Release);
+    }
+
+    /// Derives the health of the node. This reads from the DB, so it shouldn't be called
+    /// from async code.
+    pub fn status(&self) -> HealthStatus {
+        let db = self.node_context.db();
+        let synced_version = (&**db).fetch_synced_version().ok();
+        let epoch = db
+            .get_latest_ledger_info()
+            .ok()
+            .map(|ledger_info| ledger_info.ledger_info().epoch());
+
+        let state_sync_initialized = !self.require_state_sync_initialized
+            || self.state_sync_initialized.load(Ordering::Acquire);
+        HealthStatus {
+            ready: self.started.load(Ordering::Acquire) && state_sync_initialized,
+            chain_id: self.node_context.chain_id().id(),
+            synced_version,
+            epoch,
+            consensus_running: self.consensus_running.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// Starts the health server on the given runtime and returns the address it's bound to.
+/// `/live` always responds with 200 while the server runs, and `/ready` responds with 503
+/// until the node is ready.
+pub fn start_health_server(
+    runtime: &Handle,
+    address: SocketAddr,
+    health_checker: Arc<HealthChecker>,
+) -> Result<SocketAddr> {
+    let with_health_checker = warp::any().map(move || health_checker.clone());
+    let live = warp::path("live")
+        .and(warp::path::end())
+        .and(with_health_checker.clone())
+        .and_then(|health_checker| handle_probe(health_checker, false));
+    let ready = warp::path("ready")
+        .and(warp::path::end())
+        .and(with_health_checker)
+        .and_then(|health_checker| handle_probe(health_checker, true));
+
+    let _enter = runtime.enter();
+    let (address, server) = warp::serve(live.or(ready))
+        .try_bind_ephemeral(address)
+        .with_context(|| format!("Failed to bind the health server to {}", address))?;
+    runtime.spawn(server);
+    info!("Health server listening on {}", address);
+    Ok(address)
+}
+
+/// Responds to a probe with the health status. The status is derived on a blocking thread,
+/// so that its DB reads don't stall the runtime's workers.
+async fn handle_probe(
+    health_checker: Arc<HealthChecker>,
+    readiness_probe: bool,
+) -> Result<Response, Infallible> {
+    let status = match tokio::task::spawn_blocking(move || health_checker.status()).await {
+        Ok(status) => status,
+        Err(error) => {
+            return Ok(warp::reply::with_status(
+                format!("Failed to derive the node health: {}", error),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response())
+        }
+    };
+    let status_code = if readiness_probe && !status.ready {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&status), status_code).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_config::config::NodeConfig;
+    use aptos_temppath::TempPath;
+    use aptos_types::chain_id::ChainId;
+    use aptosdb::AptosDB;
+    use network::application::storage::PeerMetadataStorage;
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        time::Instant,
+    };
+
+    /// Requests the given path and returns the response's status code
+    fn get_status_code(address: SocketAddr, path: &str) -> u16 {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn test_health_probes() {
+        let db_dir = TempPath::new();
+        let node_context = NodeContext::new(
+            &NodeConfig::default(),
+            ChainId::test(),
+            Arc::new(AptosDB::new_for_test(&db_dir)),
+            PeerMetadataStorage::new(&[]),
+            Instant::now(),
+        );
+        let health_checker = HealthChecker::new(node_context, true);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let address = start_health_server(
+            runtime.handle(),
+            "127.0.0.1:0".parse().unwrap(),
+            health_checker.clone(),
+        )
+        .unwrap();
+
+        // The node is live, but not ready, until it has started and state sync has initialized
+        assert_eq!(get_status_code(address, "/live"), 200);
+        assert_eq!(get_status_code(address, "/ready"), 503);
+        health_checker.set_started(false);
+        assert_eq!(get_status_code(address, "/ready"), 503);
+        health_checker.set_state_sync_initialized();
+        assert_eq!(get_status_code(address, "/ready"), 200);
+        assert_eq!(get_status_code(address, "/live"), 200);
+    }
+}
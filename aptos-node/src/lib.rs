@@ -1,48 +1,67 @@
 // Copyright (c) Aptos
 // SPDX-License-Identifier: Apache-2.0
 
+use anyhow::{anyhow, bail, Context, Result};
 use aptos_api::runtime::bootstrap as bootstrap_api;
 use aptos_config::{
     config::{
         AptosDataClientConfig, DataStreamingServiceConfig, NetworkConfig, NodeConfig,
-        PersistableConfig, StorageServiceConfig,
+        PersistableConfig, RoleType, StorageServiceConfig,
     },
     network_id::NetworkId,
     utils::get_genesis_txn,
 };
-use aptos_data_client::aptosnet::AptosNetDataClient;
+use aptos_crypto::HashValue;
+use aptos_data_client::{aptosnet::AptosNetDataClient, AptosDataClient};
 use aptos_infallible::RwLock;
-use aptos_logger::{prelude::*, Logger};
-use aptos_metrics::{get_public_json_metrics, metric_server};
+use aptos_logger::{prelude::*, Logger, Writer};
+use aptos_mempool::{
+    ConsensusRequest, MempoolClientRequest, MempoolClientSender, SubmissionStatus,
+};
+use aptos_metrics::get_public_json_metrics;
 use aptos_state_view::account_with_state_view::AsAccountWithStateView;
 use aptos_telemetry::{
-    constants::{
-        APTOS_NODE_PUSH_METRICS, CHAIN_ID_METRIC, NODE_PUSH_TIME_SECS, PEER_ID_METRIC,
-        SYNCED_VERSION_METRIC,
-    },
+    constants::{APTOS_NODE_PUSH_METRICS, CHAIN_ID_METRIC, PEER_ID_METRIC, SYNCED_VERSION_METRIC},
     send_env_data,
 };
 use aptos_time_service::TimeService;
 use aptos_types::{
-    account_config::aptos_root_address, account_view::AccountView, chain_id::ChainId,
-    move_resource::MoveStorage, on_chain_config::ON_CHAIN_CONFIG_REGISTRY, waypoint::Waypoint,
+    account_config::aptos_root_address,
+    account_view::AccountView,
+    chain_id::ChainId,
+    move_resource::MoveStorage,
+    on_chain_config::ON_CHAIN_CONFIG_REGISTRY,
+    transaction::{SignedTransaction, Transaction, Version},
+    waypoint::Waypoint,
 };
 use aptos_vm::AptosVM;
 use aptosdb::AptosDB;
 use backup_service::start_backup_service;
-use consensus::consensus_provider::start_consensus;
-use consensus_notifications::ConsensusNotificationListener;
+use connection_churn::ConnectionChurnMonitor;
+use consensus::{
+    consensus_provider::start_consensus,
+    network_interface::{ConsensusNetworkEvents, ConsensusNetworkSender},
+};
+use consensus_notifications::{ConsensusNotificationListener, ConsensusNotifier};
 use data_streaming_service::{
     streaming_client::{new_streaming_service_client_listener_pair, StreamingServiceClient},
     streaming_service::DataStreamingService,
 };
 use debug_interface::node_debug_service::NodeDebugService;
-use event_notifications::EventSubscriptionService;
+use event_notifications::{EventSubscriptionService, ReconfigNotificationListener};
 use executor::{chunk_executor::ChunkExecutor, db_bootstrapper::maybe_bootstrap};
-use futures::channel::mpsc::channel;
+use futures::{
+    channel::{mpsc::channel, oneshot},
+    stream::{self, BoxStream, Fuse, StreamExt},
+    SinkExt,
+};
+use health::HealthChecker;
+use log_writer::RotatingFileWriter;
 use mempool_notifications::MempoolNotificationSender;
 use network::application::storage::PeerMetadataStorage;
 use network_builder::builder::NetworkBuilder;
+use once_cell::sync::OnceCell;
+use startup_timer::StartupTimer;
 use state_sync_multiplexer::{
     state_sync_v1_network_config, StateSyncMultiplexer, StateSyncRuntimes,
 };
@@ -50,15 +69,15 @@ use state_sync_v1::network::{StateSyncEvents, StateSyncSender};
 use std::{
     boxed::Box,
     collections::{HashMap, HashSet},
+    future::Future,
     io::Write,
-    net::ToSocketAddrs,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        mpsc, Arc,
     },
     thread,
-    time::Instant,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use storage_interface::{state_view::DbStateViewAtVersion, DbReaderWriter};
 use storage_service::start_storage_service_with_db;
@@ -66,25 +85,359 @@ use storage_service_client::{StorageServiceClient, StorageServiceMultiSender};
 use storage_service_server::{
     network::StorageServiceNetworkEvents, StorageReader, StorageServiceServer,
 };
+use telemetry::{
+    CONNECTED_PEERS_METRIC_PREFIX, MEMPOOL_TXN_COUNT_METRIC, TIME_TO_SYNC_METRIC,
+    VERSION_LAG_METRIC,
+};
 use tokio::runtime::{Builder, Runtime};
 use tokio_stream::wrappers::IntervalStream;
 
-const AC_SMP_CHANNEL_BUFFER_SIZE: usize = 1_024;
-const INTRA_NODE_CHANNEL_BUFFER_SIZE: usize = 1;
-const MEMPOOL_NETWORK_CHANNEL_BUFFER_SIZE: usize = 1_024;
+mod connection_churn;
+#[cfg(unix)]
+pub mod daemon;
+mod db_errors;
+pub mod db_verification;
+mod failpoints;
+mod health;
+mod log_writer;
+mod metric_server;
+mod metrics;
+mod node_context;
+mod options;
+mod startup_timer;
+pub mod telemetry;
+pub mod validation;
+mod validator_set_monitor;
+
+pub use node_context::NodeContext;
+pub use options::NodeOptions;
+
+// The maximum time each component is given to shut down when the node handle is dropped
+const COMPONENT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+// The interval at which the genesis file is polled for while waiting for it to appear
+const GENESIS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const STATE_SYNC_PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+// The interval at which a node started with `start_until_terminated` checks for termination
+const TERM_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const TIME_TO_SYNC_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// The mempool index whose size is reported as the mempool size. Every transaction in mempool
+// is in its system TTL index.
+const MEMPOOL_SIZE_INDEX_LABEL: &str = "system_ttl";
+// The maximum age of the latest ledger info for the DB to be considered up-to-date at startup.
+// This stands in for "the ledger info is from the current epoch" (see `synced_ledger_info_age`).
+const MAX_SYNCED_LEDGER_INFO_AGE: Duration = Duration::from_secs(60);
+
+// The maximum deviation of each telemetry push interval from the configured one (as a
+// fraction of it), so that nodes started together don't all push at the same instant
+const TELEMETRY_PUSH_JITTER: f64 = 0.1;
+// If set (to anything but "0" or "false"), telemetry is disabled
+const DISABLE_TELEMETRY_ENV_VAR: &str = "APTOS_DISABLE_TELEMETRY";
+
+// The git revision the node was built from (if provided by the build environment)
+const GIT_SHA: Option<&str> = option_env!("GIT_SHA");
+
+// The number of seconds it took the node to sync to the chain tip after starting up
+static TIME_TO_SYNC_SECS: OnceCell<u64> = OnceCell::new();
 
 pub struct AptosHandle {
-    _api: Runtime,
-    _backup: Runtime,
-    _consensus_runtime: Option<Runtime>,
-    _debug: NodeDebugService,
-    _mempool: Runtime,
-    _network_runtimes: Vec<Runtime>,
-    _state_sync_runtimes: StateSyncRuntimes,
-    _telemetry_runtime: Runtime,
+    api: Option<Runtime>,
+    backup: Option<Runtime>,
+    consensus_runtime: Option<Runtime>,
+    _legacy_storage_service: Option<thread::JoinHandle<()>>,
+    debug: Option<NodeDebugService>,
+    mempool: Option<Runtime>,
+    metric_servers: Vec<Runtime>,
+    mp_client_sender: Option<MempoolClientSender>,
+    network_runtimes: Vec<Runtime>,
+    node_context: Arc<NodeContext>,
+    pending_consensus: Option<PendingConsensus>,
+    state_sync_runtimes: Option<StateSyncRuntimes>,
+    // On nodes that don't wait for state sync at startup, the runtimes are held by a helper
+    // thread until state sync has initialized, which then sends them back on this channel
+    initializing_state_sync: Option<mpsc::Receiver<StateSyncRuntimes>>,
+    telemetry_runtime: Option<Runtime>,
+}
+
+/// A snapshot of the state of a running node
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeStatus {
+    pub chain_id: ChainId,
+    /// The version, root hash and epoch of the latest ledger info (if it could be read)
+    pub latest_ledger_version: Option<Version>,
+    pub latest_ledger_root_hash: Option<HashValue>,
+    pub epoch: Option<u64>,
+    pub num_network_runtimes: usize,
+    pub consensus_enabled: bool,
+}
+
+/// Everything needed to start consensus on a validator, held until consensus is started
+struct PendingConsensus {
+    node_config: NodeConfig,
+    network_sender: ConsensusNetworkSender,
+    network_events: ConsensusNetworkEvents,
+    consensus_notifier: ConsensusNotifier,
+    consensus_to_mempool_sender: futures::channel::mpsc::Sender<ConsensusRequest>,
+    db_rw: DbReaderWriter,
+    reconfig_subscription: ReconfigNotificationListener,
+    peer_metadata_storage: Arc<PeerMetadataStorage>,
+    waypoint: Waypoint,
+    always_wait_for_state_sync: bool,
+    health_checker: Option<Arc<HealthChecker>>,
+    term: Option<Arc<AtomicBool>>,
+}
+
+impl PendingConsensus {
+    /// Makes sure that state sync is caught up at least to the waypoint, as there is no
+    /// sense in starting consensus prior to that. If the DB already satisfies the waypoint
+    /// (e.g., on a restart), state sync can initialize concurrently with consensus, so this
+    /// doesn't wait. Returns the state sync runtimes and whether it waited.
+    fn wait_for_state_sync(
+        &self,
+        state_sync_runtimes: StateSyncRuntimes,
+        timeout: Option<Duration>,
+    ) -> Result<(StateSyncRuntimes, bool)> {
+        // TODO: Note that we need the networking layer to be able to discover & connect to the
+        // peers with potentially outdated network identity public keys.
+        if !self.always_wait_for_state_sync {
+            if let Some(ledger_info_age) = synced_ledger_info_age(&self.db_rw, self.waypoint) {
+                info!(
+                    waypoint_version = self.waypoint.version(),
+                    ledger_info_age_ms = ledger_info_age.as_millis() as u64,
+                    "The DB already satisfies the waypoint, skipping the wait for state sync and \
+                    starting consensus while state sync initializes"
+                );
+                self.set_state_sync_initialized();
+                return Ok((state_sync_runtimes, false));
+            }
+        }
+
+        debug!("Wait until state sync is initialized");
+        let state_sync_runtimes = wait_for_state_sync_initialization(
+            state_sync_runtimes,
+            timeout,
+            self.waypoint,
+            &self.db_rw,
+            &self.peer_metadata_storage,
+            self.term.as_deref(),
+        )?;
+        self.set_state_sync_initialized();
+        Ok((state_sync_runtimes, true))
+    }
+
+    fn set_state_sync_initialized(&self) {
+        if let Some(health_checker) = &self.health_checker {
+            health_checker.set_state_sync_initialized();
+        }
+    }
+
+    fn start(self) -> Runtime {
+        let consensus_runtime = start_consensus(
+            &self.node_config,
+            self.network_sender,
+            self.network_events,
+            Arc::new(self.consensus_notifier),
+            self.consensus_to_mempool_sender,
+            self.db_rw,
+            self.reconfig_subscription,
+            self.peer_metadata_storage,
+        );
+        if let Some(health_checker) = self.health_checker {
+            health_checker.set_consensus_running();
+        }
+        consensus_runtime
+    }
+}
+
+impl AptosHandle {
+    /// Starts consensus on a validator started with a delayed consensus start, once state
+    /// sync has initialized. This blocks until then, without a timeout (as giving up would
+    /// leave state sync stopped). Calling this when consensus is already running (or on a
+    /// node that doesn't run consensus) does nothing.
+    pub fn start_consensus(&mut self) -> Result<()> {
+        let pending_consensus = match self.pending_consensus.take() {
+            Some(pending_consensus) => pending_consensus,
+            None => {
+                warn!("Consensus is already running (or this node doesn't run it), ignoring the request to start it");
+                return Ok(());
+            }
+        };
+        let state_sync_runtimes = self
+            .state_sync_runtimes
+            .take()
+            .ok_or_else(|| anyhow!("Unable to start consensus, state sync isn't running!"))?;
+
+        let (state_sync_runtimes, _) =
+            pending_consensus.wait_for_state_sync(state_sync_runtimes, None)?;
+        self.state_sync_runtimes = Some(state_sync_runtimes);
+        log_state_sync_initialized(self.node_context.start_time());
+        info!("Starting consensus");
+        self.consensus_runtime = Some(pending_consensus.start());
+        Ok(())
+    }
+
+    /// Returns the current status of the node. The latest ledger info is read from the DB's
+    /// in-memory cache, so this is cheap enough to poll (e.g., from test harnesses).
+    pub fn status(&self) -> NodeStatus {
+        let latest_ledger_info = self.node_context.db().get_latest_ledger_info().ok();
+        let latest_ledger_info = latest_ledger_info
+            .as_ref()
+            .map(|ledger_info| ledger_info.ledger_info());
+        NodeStatus {
+            chain_id: self.node_context.chain_id(),
+            latest_ledger_version: latest_ledger_info.map(|ledger_info| ledger_info.version()),
+            latest_ledger_root_hash: latest_ledger_info
+                .map(|ledger_info| ledger_info.transaction_accumulator_hash()),
+            epoch: latest_ledger_info.map(|ledger_info| ledger_info.epoch()),
+            num_network_runtimes: self.network_runtimes.len(),
+            consensus_enabled: self.consensus_runtime.is_some(),
+        }
+    }
+
+    /// Submits a transaction directly to mempool, without going through the REST API. The
+    /// transaction skips the API's transport level checks (e.g., request size and content
+    /// type), but is still subject to full mempool validation. As with the API, the returned
+    /// future waits if the mempool request channel is full.
+    pub fn submit_transaction(
+        &self,
+        txn: SignedTransaction,
+    ) -> impl Future<Output = Result<SubmissionStatus>> {
+        let mp_client_sender = self.mp_client_sender.clone();
+        async move {
+            let mut mp_client_sender = match mp_client_sender {
+                Some(mp_client_sender) => mp_client_sender,
+                None => bail!("This node doesn't run mempool and can't accept transactions!"),
+            };
+            let (req_sender, callback) = oneshot::channel();
+            mp_client_sender
+                .send(MempoolClientRequest::SubmitTransaction(txn, req_sender))
+                .await?;
+            callback.await?
+        }
+    }
+}
+
+impl Drop for AptosHandle {
+    /// Tears down the components in dependency order, i.e., those that drive the others
+    /// (and use the DB) first. Each runtime gets a bounded amount of time to shut down, after
+    /// which its remaining tasks are abandoned.
+    fn drop(&mut self) {
+        shutdown_runtime("consensus", self.consensus_runtime.take());
+        shutdown_runtime("mempool", self.mempool.take());
+        if let Some(initializing_state_sync) = self.initializing_state_sync.take() {
+            match initializing_state_sync.try_recv() {
+                Ok(state_sync_runtimes) => self.state_sync_runtimes = Some(state_sync_runtimes),
+                Err(_) => {
+                    warn!("State sync hasn't initialized yet, abandoning its runtimes")
+                }
+            }
+        }
+        if let Some(state_sync_runtimes) = self.state_sync_runtimes.take() {
+            let start_time = Instant::now();
+            drop(state_sync_runtimes);
+            log_slow_shutdown("state sync", start_time);
+        }
+        for runtime in self.network_runtimes.drain(..) {
+            shutdown_runtime("network", Some(runtime));
+        }
+        shutdown_runtime("api", self.api.take());
+        shutdown_runtime("backup", self.backup.take());
+        shutdown_runtime("telemetry", self.telemetry_runtime.take());
+        if let Some(debug) = self.debug.take() {
+            let start_time = Instant::now();
+            drop(debug);
+            log_slow_shutdown("debug", start_time);
+        }
+        for runtime in self.metric_servers.drain(..) {
+            shutdown_runtime("metric server", Some(runtime));
+        }
+    }
 }
 
-pub fn start(config: &NodeConfig, log_file: Option<PathBuf>) {
+/// Shuts down the given runtime (if any), waiting at most `COMPONENT_SHUTDOWN_TIMEOUT` for
+/// its blocking tasks. Pending async tasks are cancelled rather than awaited.
+fn shutdown_runtime(name: &str, runtime: Option<Runtime>) {
+    if let Some(runtime) = runtime {
+        let start_time = Instant::now();
+        runtime.shutdown_timeout(COMPONENT_SHUTDOWN_TIMEOUT);
+        log_slow_shutdown(name, start_time);
+    }
+}
+
+fn log_slow_shutdown(name: &str, start_time: Instant) {
+    let elapsed = start_time.elapsed();
+    if elapsed >= COMPONENT_SHUTDOWN_TIMEOUT {
+        warn!(
+            "The {} component took {} ms to shut down (timeout: {} ms)",
+            name,
+            elapsed.as_millis(),
+            COMPONENT_SHUTDOWN_TIMEOUT.as_millis()
+        );
+    }
+}
+
+/// Starts the node and blocks until the process receives a termination signal (SIGTERM,
+/// SIGINT or SIGHUP on unix, ctrl-c elsewhere), after which the node is torn down.
+pub fn start(config: &NodeConfig, options: NodeOptions, log_file: Option<PathBuf>) {
+    let term = Arc::new(AtomicBool::new(false));
+    install_termination_handler(term.clone());
+    start_until_terminated(config, options, log_file, term);
+}
+
+/// Installs a signal handler that sets `term` and wakes up the calling thread. A second
+/// signal exits the process immediately, e.g., if the node is stuck starting up or shutting
+/// down.
+fn install_termination_handler(term: Arc<AtomicBool>) {
+    let main_thread = thread::current();
+    ctrlc::set_handler(move || {
+        if term.swap(true, Ordering::AcqRel) {
+            eprintln!("Received a second termination signal, exiting immediately");
+            std::process::exit(1);
+        }
+        main_thread.unpark();
+    })
+    .expect("Failed to install the termination signal handler");
+}
+
+/// Starts the node and blocks until `term` is set, at which point the node is torn down and
+/// this returns. The calling thread is parked in between, so whoever sets `term` should
+/// also unpark it (otherwise it is noticed within `TERM_CHECK_INTERVAL`). Setting `term`
+/// during startup aborts the wait for state sync to initialize.
+pub fn start_until_terminated(
+    config: &NodeConfig,
+    mut options: NodeOptions,
+    log_file: Option<PathBuf>,
+    term: Arc<AtomicBool>,
+) {
+    options.term = Some(term.clone());
+    let node_handle = match start_and_return(config, options, log_file) {
+        Ok(node_handle) => node_handle,
+        Err(error) if term.load(Ordering::Acquire) => {
+            info!("Termination requested during startup: {:?}", error);
+            return;
+        }
+        Err(error) => {
+            error!("Failed to start the node: {:?}", error);
+            eprintln!("Failed to start the node: {:?}", error);
+            std::process::exit(1);
+        }
+    };
+
+    while !term.load(Ordering::Acquire) {
+        thread::park_timeout(TERM_CHECK_INTERVAL);
+    }
+    info!("Termination requested, shutting down the node");
+    drop(node_handle);
+}
+
+/// Does everything `start` does (i.e., sets up the panic handler, logger and failpoints
+/// before starting the node), but returns the node handle instead of blocking, so that the
+/// caller controls the node's lifetime. As the logger is global, this must only be called
+/// once per process.
+pub fn start_and_return(
+    config: &NodeConfig,
+    options: NodeOptions,
+    log_file: Option<PathBuf>,
+) -> Result<AptosHandle> {
     crash_handler::setup_panic_handler();
 
     let mut logger = aptos_logger::Logger::new();
@@ -97,30 +450,24 @@ pub fn start(config: &NodeConfig, log_file: Option<PathBuf>) {
         logger.enable_backtrace();
     }
     if let Some(log_file) = log_file {
-        logger.printer(Box::new(FileWriter::new(log_file)));
+        let printer: Box<dyn Writer> = match options.log_file_max_bytes {
+            Some(max_bytes) => Box::new(
+                RotatingFileWriter::new(log_file.clone(), max_bytes, options.log_file_max_files)
+                    .with_context(|| format!("Failed to open the log file {:?}", log_file))?,
+            ),
+            None => Box::new(FileWriter::new(log_file)),
+        };
+        logger.printer(printer);
     }
     let logger = Some(logger.build());
 
     // Let's now log some important information, since the logger is set up
     info!(config = config, "Loaded AptosNode config");
 
-    if fail::has_failpoints() {
-        warn!("Failpoints is enabled");
-        if let Some(failpoints) = &config.failpoints {
-            for (point, actions) in failpoints {
-                fail::cfg(point, actions).expect("fail to set actions for failpoint");
-            }
-        }
-    } else if config.failpoints.is_some() {
-        warn!("failpoints is set in config, but the binary doesn't compile with this feature");
-    }
+    failpoints::setup_failpoints(config.failpoints.as_ref(), fail::has_failpoints())
+        .context("Failed to set up the failpoints")?;
 
-    let _node_handle = setup_environment(config, logger);
-    let term = Arc::new(AtomicBool::new(false));
-
-    while !term.load(Ordering::Acquire) {
-        std::thread::park();
-    }
+    setup_environment(config, &options, logger)
 }
 
 pub fn load_test_environment<R>(
@@ -129,6 +476,7 @@ pub fn load_test_environment<R>(
     lazy: bool,
     genesis_modules: Vec<Vec<u8>>,
     rng: R,
+    options: NodeOptions,
 ) where
     R: ::rand::RngCore + ::rand::CryptoRng,
 {
@@ -214,37 +562,57 @@ pub fn load_test_environment<R>(
 
     println!("\nAptos is running, press ctrl-c to exit\n");
 
-    start(&config, Some(log_file))
+    start(&config, options, Some(log_file))
 }
 
 // Fetch chain ID from on-chain resource
-fn fetch_chain_id(db: &DbReaderWriter) -> ChainId {
+fn fetch_chain_id(db: &DbReaderWriter) -> Result<ChainId> {
     let synced_version = (&*db.reader)
         .fetch_synced_version()
-        .expect("[aptos-node] failed fetching synced version.");
+        .context("[aptos-node] failed fetching synced version")?;
     let db_state_view = db
         .reader
         .state_view_at_version(Some(synced_version))
-        .expect("[aptos-node] failed to create db state view");
-    db_state_view
+        .context("[aptos-node] failed to create db state view")?;
+    Ok(db_state_view
         .as_account_with_state_view(&aptos_root_address())
         .get_chain_id_resource()
-        .expect("[aptos-node] failed to get chain ID resource")
-        .expect("[aptos-node] missing chain ID resource")
-        .chain_id()
+        .context("[aptos-node] failed to get chain ID resource")?
+        .ok_or_else(|| anyhow!("[aptos-node] missing chain ID resource"))?
+        .chain_id())
 }
 
-fn setup_debug_interface(config: &NodeConfig, logger: Option<Arc<Logger>>) -> NodeDebugService {
-    let addr = format!(
-        "{}:{}",
-        config.debug_interface.address, config.debug_interface.admission_control_node_debug_port,
-    )
-    .to_socket_addrs()
-    .unwrap()
-    .next()
-    .unwrap();
+/// Publishes the build, chain and role of the node through the node info metric. Any
+/// previously published info is replaced.
+fn set_node_info_metric(chain_id: ChainId, role: RoleType) {
+    metrics::NODE_INFO.reset();
+    metrics::NODE_INFO
+        .with_label_values(&[
+            GIT_SHA.unwrap_or("unknown"),
+            env!("CARGO_PKG_VERSION"),
+            &chain_id.id().to_string(),
+            role.as_str(),
+        ])
+        .set(1);
+}
+
+/// Starts the debug interface on the explicitly given address, or otherwise on the first
+/// bindable address the configured debug interface host resolves to.
+fn setup_debug_interface(
+    config: &NodeConfig,
+    options: &NodeOptions,
+    logger: Option<Arc<Logger>>,
+) -> Result<NodeDebugService> {
+    let socket_addr = match options.debug_interface_address {
+        Some(socket_addr) => socket_addr,
+        None => validation::resolve_bindable_address(
+            &config.debug_interface.address,
+            config.debug_interface.admission_control_node_debug_port,
+        )?,
+    };
+    info!("Starting the debug interface on {}", socket_addr);
 
-    NodeDebugService::new(addr, logger, config)
+    Ok(NodeDebugService::new(socket_addr, logger, config))
 }
 
 fn create_state_sync_runtimes<M: MempoolNotificationSender + 'static>(
@@ -261,13 +629,16 @@ fn create_state_sync_runtimes<M: MempoolNotificationSender + 'static>(
     waypoint: Waypoint,
     event_subscription_service: EventSubscriptionService,
     db_rw: DbReaderWriter,
-) -> StateSyncRuntimes {
+    chunk_executor: Arc<ChunkExecutor<AptosVM>>,
+    worker_threads: Option<usize>,
+) -> Result<(StateSyncRuntimes, AptosNetDataClient)> {
     // Start the state sync storage service
     let storage_service_runtime = setup_state_sync_storage_service(
         node_config.state_sync.storage_service,
         storage_service_server_network_handles,
         &db_rw,
-    );
+        worker_threads,
+    )?;
 
     // Start the data client
     let (aptos_data_client, aptos_data_client_runtime) = setup_aptos_data_client(
@@ -275,18 +646,15 @@ fn create_state_sync_runtimes<M: MempoolNotificationSender + 'static>(
         node_config.state_sync.aptos_data_client,
         storage_service_client_network_handles,
         peer_metadata_storage,
-    );
+        worker_threads,
+    )?;
 
     // Start the data streaming service
     let (streaming_service_client, streaming_service_runtime) = setup_data_streaming_service(
         node_config.state_sync.data_streaming_service,
         aptos_data_client.clone(),
-    );
-
-    // Create the chunk executor
-    let chunk_executor = Arc::new(
-        ChunkExecutor::<AptosVM>::new(db_rw.clone()).expect("Unable to create the chunk executor!"),
-    );
+        worker_threads,
+    )?;
 
     // Create the state sync multiplexer
     let state_sync_multiplexer = StateSyncMultiplexer::new(
@@ -298,23 +666,25 @@ fn create_state_sync_runtimes<M: MempoolNotificationSender + 'static>(
         node_config,
         waypoint,
         event_subscription_service,
-        aptos_data_client,
+        aptos_data_client.clone(),
         streaming_service_client,
     );
 
-    // Create and return the new state sync handle
-    StateSyncRuntimes::new(
+    // Create and return the new state sync handle (and the data client)
+    let state_sync_runtimes = StateSyncRuntimes::new(
         aptos_data_client_runtime,
         state_sync_multiplexer,
         storage_service_runtime,
         streaming_service_runtime,
-    )
+    );
+    Ok((state_sync_runtimes, aptos_data_client))
 }
 
 fn setup_data_streaming_service(
     config: DataStreamingServiceConfig,
     aptos_data_client: AptosNetDataClient,
-) -> (StreamingServiceClient, Runtime) {
+    worker_threads: Option<usize>,
+) -> Result<(StreamingServiceClient, Runtime)> {
     // Create the data streaming service
     let (streaming_service_client, streaming_service_listener) =
         new_streaming_service_client_listener_pair();
@@ -322,14 +692,11 @@ fn setup_data_streaming_service(
         DataStreamingService::new(config, aptos_data_client, streaming_service_listener);
 
     // Start the data streaming service
-    let streaming_service_runtime = Builder::new_multi_thread()
-        .thread_name("data-streaming-service")
-        .enable_all()
-        .build()
-        .expect("Failed to create data streaming service!");
+    let streaming_service_runtime = create_runtime("data-streaming-service".into(), worker_threads)
+        .context("Failed to create the data streaming service runtime")?;
     streaming_service_runtime.spawn(data_streaming_service.start_service());
 
-    (streaming_service_client, streaming_service_runtime)
+    Ok((streaming_service_client, streaming_service_runtime))
 }
 
 fn setup_aptos_data_client(
@@ -337,7 +704,8 @@ fn setup_aptos_data_client(
     aptos_data_client_config: AptosDataClientConfig,
     network_handles: HashMap<NetworkId, storage_service_client::StorageServiceNetworkSender>,
     peer_metadata_storage: Arc<PeerMetadataStorage>,
-) -> (AptosNetDataClient, Runtime) {
+    worker_threads: Option<usize>,
+) -> Result<(AptosNetDataClient, Runtime)> {
     // Combine all storage service client handles
     let network_client = StorageServiceClient::new(
         StorageServiceMultiSender::new(network_handles),
@@ -345,11 +713,8 @@ fn setup_aptos_data_client(
     );
 
     // Create a new runtime for the data client
-    let aptos_data_client_runtime = Builder::new_multi_thread()
-        .thread_name("aptos-data-client")
-        .enable_all()
-        .build()
-        .expect("Failed to create aptos data client!");
+    let aptos_data_client_runtime = create_runtime("aptos-data-client".into(), worker_threads)
+        .context("Failed to create the aptos data client runtime")?;
 
     // Create the data client and spawn the data poller
     let (aptos_data_client, data_summary_poller) = AptosNetDataClient::new(
@@ -361,20 +726,19 @@ fn setup_aptos_data_client(
     );
     aptos_data_client_runtime.spawn(data_summary_poller.start_poller());
 
-    (aptos_data_client, aptos_data_client_runtime)
+    Ok((aptos_data_client, aptos_data_client_runtime))
 }
 
 fn setup_state_sync_storage_service(
     config: StorageServiceConfig,
     network_handles: Vec<StorageServiceNetworkEvents>,
     db_rw: &DbReaderWriter,
-) -> Runtime {
+    worker_threads: Option<usize>,
+) -> Result<Runtime> {
     // Create a new state sync storage service runtime
-    let storage_service_runtime = Builder::new_multi_thread()
-        .thread_name("storage-service-server")
-        .enable_all()
-        .build()
-        .expect("Failed to start the AptosNet storage-service runtime.");
+    let storage_service_runtime =
+        create_runtime("storage-service-server".into(), worker_threads)
+            .context("Failed to create the AptosNet storage service runtime")?;
 
     // Spawn all state sync storage service servers on the same runtime
     let storage_reader = StorageReader::new(config, Arc::clone(&db_rw.reader));
@@ -389,71 +753,160 @@ fn setup_state_sync_storage_service(
         storage_service_runtime.spawn(service.start());
     }
 
-    storage_service_runtime
+    Ok(storage_service_runtime)
 }
 
-async fn periodic_telemetry_dump(node_config: NodeConfig, db: DbReaderWriter) {
-    use futures::stream::StreamExt;
-    let mut dump_interval = IntervalStream::new(tokio::time::interval(
-        std::time::Duration::from_secs(NODE_PUSH_TIME_SECS),
-    ))
-    .fuse();
+/// Returns the given interval, randomly shifted by up to `TELEMETRY_PUSH_JITTER` of it
+fn jittered_interval<R: ::rand::Rng>(interval: Duration, rng: &mut R) -> Duration {
+    let jitter = rng.gen_range(-TELEMETRY_PUSH_JITTER..=TELEMETRY_PUSH_JITTER);
+    interval.mul_f64(1.0 + jitter)
+}
 
+/// Returns the number of versions the given synced version is behind the highest version
+/// advertised by peers, or `None` if no peer has advertised any data yet
+fn version_lag(aptos_data_client: &AptosNetDataClient, synced_version: Version) -> Option<u64> {
+    aptos_data_client
+        .get_global_data_summary()
+        .advertised_data
+        .highest_synced_ledger_info()
+        .map(|ledger_info| {
+            ledger_info
+                .ledger_info()
+                .version()
+                .saturating_sub(synced_version)
+        })
+}
+
+async fn periodic_telemetry_dump(
+    node_context: Arc<NodeContext>,
+    push_interval: Duration,
+    aptos_data_client: Option<AptosNetDataClient>,
+) {
     info!("periodic_telemetry_dump task started");
 
+    // Delay the first push by a random fraction of the interval, so that nodes started
+    // together don't push on the same boundary
+    let mut next_push_delay = push_interval.mul_f64(::rand::random::<f64>());
     loop {
-        futures::select! {
-            _ = dump_interval.select_next_some() => {
-
-                // Build the params from internal prometheus metrics
-                let mut metrics_params: HashMap<String, String> = HashMap::new();
+        tokio::time::sleep(next_push_delay).await;
+        next_push_delay = jittered_interval(push_interval, &mut ::rand::thread_rng());
+
+        let peer_id = match node_context.node_config().peer_id() {
+            Some(p) => p.to_string(),
+            None => String::new(),
+        };
+        let metrics_params = telemetry_metrics(&node_context, aptos_data_client.as_ref());
+        send_env_data(APTOS_NODE_PUSH_METRICS.to_string(), peer_id, metrics_params).await;
+    }
+}
 
-                let met = get_public_json_metrics();
-                for (k, v) in &met {
-                    metrics_params.insert(k.to_string(), v.to_string());
-                }
+/// Builds the params pushed to the telemetry service
+fn telemetry_metrics(
+    node_context: &NodeContext,
+    aptos_data_client: Option<&AptosNetDataClient>,
+) -> HashMap<String, String> {
+    // Build the params from internal prometheus metrics
+    let mut metrics_params: HashMap<String, String> = HashMap::new();
+
+    let met = get_public_json_metrics();
+    for (k, v) in &met {
+        metrics_params.insert(k.to_string(), v.to_string());
+    }
 
-                // get some data we do not currently have metrics for
-                let chain_id = fetch_chain_id(&db).id(); // get the chain_id as its u8 id for consistency of schema
-                let peer_id = match node_config.peer_id() {
-                    Some(p) => p.to_string(),
-                    None => String::new()
-                };
-                let synced_version = (&*db.reader).fetch_synced_version().unwrap_or(0);
+    // get some data we do not currently have metrics for
+    let chain_id = node_context.chain_id().id(); // get the chain_id as its u8 id for consistency of schema
+    let peer_id = match node_context.node_config().peer_id() {
+        Some(p) => p.to_string(),
+        None => String::new(),
+    };
+    let synced_version = node_context.synced_version().unwrap_or(0);
 
-                metrics_params.insert(SYNCED_VERSION_METRIC.to_string(), synced_version.to_string());
-                metrics_params.insert(CHAIN_ID_METRIC.to_string(), chain_id.to_string());
-                metrics_params.insert(PEER_ID_METRIC.to_string(), peer_id.to_string());
-                send_env_data(APTOS_NODE_PUSH_METRICS.to_string(), peer_id.to_string(), metrics_params).await;
-            }
-        }
+    metrics_params.insert(
+        SYNCED_VERSION_METRIC.to_string(),
+        synced_version.to_string(),
+    );
+    metrics_params.insert(CHAIN_ID_METRIC.to_string(), chain_id.to_string());
+    metrics_params.insert(PEER_ID_METRIC.to_string(), peer_id.to_string());
+    if let Some(time_to_sync_secs) = TIME_TO_SYNC_SECS.get() {
+        metrics_params.insert(
+            TIME_TO_SYNC_METRIC.to_string(),
+            time_to_sync_secs.to_string(),
+        );
+    }
+    if let Some(version_lag) = aptos_data_client
+        .and_then(|aptos_data_client| version_lag(aptos_data_client, synced_version))
+    {
+        metrics_params.insert(VERSION_LAG_METRIC.to_string(), version_lag.to_string());
+    }
+    let mempool_txn_count = aptos_mempool::counters::CORE_MEMPOOL_INDEX_SIZE
+        .with_label_values(&[MEMPOOL_SIZE_INDEX_LABEL])
+        .get();
+    metrics_params.insert(
+        MEMPOOL_TXN_COUNT_METRIC.to_string(),
+        mempool_txn_count.to_string(),
+    );
+    for (network_id, connected_peers) in
+        count_connected_peers_per_network(node_context.peer_metadata_storage())
+    {
+        metrics_params.insert(
+            format!(
+                "{}{}",
+                CONNECTED_PEERS_METRIC_PREFIX,
+                network_id.as_str().to_lowercase()
+            ),
+            connected_peers.to_string(),
+        );
     }
+    metrics_params
 }
 
-async fn periodic_state_dump(node_config: NodeConfig, db: DbReaderWriter) {
-    use futures::stream::StreamExt;
+/// Returns a stream ticking at the given interval, or a stream that never ticks if the
+/// interval is zero
+fn dump_interval(interval: Duration) -> Fuse<BoxStream<'static, ()>> {
+    if interval.is_zero() {
+        stream::pending().boxed().fuse()
+    } else {
+        IntervalStream::new(tokio::time::interval(interval))
+            .map(|_| ())
+            .boxed()
+            .fuse()
+    }
+}
 
+async fn periodic_state_dump(
+    node_context: Arc<NodeContext>,
+    connection_churn_monitor: ConnectionChurnMonitor,
+    config_dump_interval: Duration,
+    ledger_dump_interval: Duration,
+) {
+    if config_dump_interval.is_zero() && ledger_dump_interval.is_zero() {
+        info!("Periodic state dumps are disabled");
+        return;
+    }
     let args: Vec<String> = ::std::env::args().collect();
 
-    // Once an hour
-    let mut config_interval = IntervalStream::new(tokio::time::interval(
-        std::time::Duration::from_secs(60 * 60),
-    ))
-    .fuse();
-    // Once a minute
-    let mut version_interval =
-        IntervalStream::new(tokio::time::interval(std::time::Duration::from_secs(60))).fuse();
+    let mut config_interval = dump_interval(config_dump_interval);
+    let mut version_interval = dump_interval(ledger_dump_interval);
+    // The latest ledger version as of the previous dump
+    let mut previous_version = None;
 
     info!("periodic_state_dump task started");
 
     loop {
         futures::select! {
             _ = config_interval.select_next_some() => {
-                info!(config = node_config, args = args, "config and command line arguments");
+                info!(config = node_context.node_config(), args = args, "config and command line arguments");
+
+                let most_reconnected_peers: Vec<String> = connection_churn_monitor
+                    .most_reconnected_peers(3)
+                    .into_iter()
+                    .map(|(peer, reconnects)| format!("{} ({} reconnects)", peer, reconnects))
+                    .collect();
+                info!(most_reconnected_peers = most_reconnected_peers, "most reconnected peers");
             }
             _ = version_interval.select_next_some() => {
-                let chain_id = fetch_chain_id(&db);
-                let ledger_info = if let Ok(ledger_info) = db.reader.get_latest_ledger_info() {
+                let chain_id = node_context.chain_id();
+                let ledger_info = if let Some(ledger_info) = node_context.latest_ledger_info() {
                     ledger_info
                 } else {
                     warn!("unable to query latest ledger info");
@@ -462,11 +915,17 @@ async fn periodic_state_dump(node_config: NodeConfig, db: DbReaderWriter) {
 
                 let latest_ledger_verion = ledger_info.ledger_info().version();
                 let root_hash = ledger_info.ledger_info().transaction_accumulator_hash();
+                let committed_txns = previous_version
+                    .map(|previous_version: Version| latest_ledger_verion.saturating_sub(previous_version));
+                previous_version = Some(latest_ledger_verion);
 
                 info!(
                     chain_id = chain_id,
                     latest_ledger_verion = latest_ledger_verion,
                     root_hash = root_hash,
+                    epoch = ledger_info.ledger_info().epoch(),
+                    ledger_timestamp_usecs = ledger_info.ledger_info().timestamp_usecs(),
+                    committed_txns_since_last_dump = committed_txns,
                     "latest ledger version and its corresponding root hash"
                 );
             }
@@ -474,50 +933,426 @@ async fn periodic_state_dump(node_config: NodeConfig, db: DbReaderWriter) {
     }
 }
 
-pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>) -> AptosHandle {
-    let debug_if = setup_debug_interface(node_config, logger);
+/// Blocks until state sync has caught up to the waypoint, logging progress every
+/// `STATE_SYNC_PROGRESS_LOG_INTERVAL`. Fails with a diagnostic if `timeout` elapses first
+/// (without a timeout, this waits indefinitely). Also fails within `TERM_CHECK_INTERVAL` of
+/// `term` being set, so that a termination signal isn't ignored for the rest of the wait.
+fn wait_for_state_sync_initialization(
+    state_sync_runtimes: StateSyncRuntimes,
+    timeout: Option<Duration>,
+    waypoint: Waypoint,
+    db: &DbReaderWriter,
+    peer_metadata_storage: &PeerMetadataStorage,
+    term: Option<&AtomicBool>,
+) -> Result<StateSyncRuntimes> {
+    let initialized_receiver = spawn_state_sync_initialization_wait(state_sync_runtimes, || {})?;
+
+    let start_time = Instant::now();
+    let mut last_progress_log = Instant::now();
+    loop {
+        let wait_duration = match timeout {
+            Some(timeout) => {
+                let remaining = timeout.saturating_sub(start_time.elapsed());
+                TERM_CHECK_INTERVAL.min(remaining)
+            }
+            None => TERM_CHECK_INTERVAL,
+        };
+        match initialized_receiver.recv_timeout(wait_duration) {
+            Ok(state_sync_runtimes) => return Ok(state_sync_runtimes),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                bail!("State sync initialization thread terminated unexpectedly!")
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if term.map_or(false, |term| term.load(Ordering::Acquire)) {
+                    bail!("Termination requested while waiting for state sync to initialize");
+                }
+                let timed_out = timeout.map_or(false, |timeout| start_time.elapsed() >= timeout);
+                if !timed_out && last_progress_log.elapsed() < STATE_SYNC_PROGRESS_LOG_INTERVAL {
+                    continue;
+                }
+                last_progress_log = Instant::now();
+
+                let synced_version = (&*db.reader).fetch_synced_version().ok();
+                let connected_peers = count_connected_peers(peer_metadata_storage);
+                let elapsed_secs = start_time.elapsed().as_secs();
+                if timed_out {
+                    bail!(
+                        "State sync failed to initialize within {} seconds! Synced version: {:?}, \
+                        waypoint version: {}, connected peers: {}",
+                        elapsed_secs,
+                        synced_version,
+                        waypoint.version(),
+                        connected_peers
+                    );
+                }
+                info!(
+                    synced_version = synced_version,
+                    waypoint_version = waypoint.version(),
+                    connected_peers = connected_peers,
+                    elapsed_secs = elapsed_secs,
+                    "Still waiting for state sync to initialize"
+                );
+            }
+        }
+    }
+}
 
-    let metrics_port = node_config.debug_interface.metrics_server_port;
-    let metric_host = node_config.debug_interface.address.clone();
-    thread::spawn(move || metric_server::start_server(metric_host, metrics_port, false));
-    let public_metrics_port = node_config.debug_interface.public_metrics_server_port;
-    let public_metric_host = node_config.debug_interface.address.clone();
-    thread::spawn(move || {
-        metric_server::start_server(public_metric_host, public_metrics_port, true)
-    });
+/// Waits for state sync to initialize on a separate thread (as block_until_initialized()
+/// can't be interrupted). Once it has, `on_initialized` is called on that thread and the
+/// runtimes are handed back over the returned channel.
+fn spawn_state_sync_initialization_wait(
+    state_sync_runtimes: StateSyncRuntimes,
+    on_initialized: impl FnOnce() + Send + 'static,
+) -> Result<mpsc::Receiver<StateSyncRuntimes>> {
+    let (initialized_sender, initialized_receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("state-sync-init".into())
+        .spawn(move || {
+            state_sync_runtimes.block_until_initialized();
+            on_initialized();
+            let _ = initialized_sender.send(state_sync_runtimes);
+        })
+        .context("Failed to spawn the state sync initialization thread")?;
+    Ok(initialized_receiver)
+}
+
+/// Records and logs the time since startup at which state sync initialized, for
+/// initializations that complete after the startup summary was logged
+fn log_state_sync_initialized(start_time: Instant) {
+    let duration = startup_timer::record_state_sync_initialized(start_time);
+    info!(
+        state_sync_initialized_ms = duration.as_millis() as u64,
+        "State sync initialized"
+    );
+}
+
+/// Creates a new multi-threaded runtime with the given thread name. If `worker_threads`
+/// isn't specified, tokio's default (one worker per CPU core) is used.
+fn create_runtime(thread_name: String, worker_threads: Option<usize>) -> Result<Runtime> {
+    let mut builder = Builder::new_multi_thread();
+    builder.thread_name(thread_name.clone()).enable_all();
+    match worker_threads {
+        Some(0) => bail!(
+            "Runtime {} must have at least one worker thread!",
+            thread_name
+        ),
+        Some(worker_threads) => {
+            builder.worker_threads(worker_threads);
+            info!(
+                "Runtime {} uses {} worker threads",
+                thread_name, worker_threads
+            );
+        }
+        None => info!(
+            "Runtime {} uses the default number of worker threads",
+            thread_name
+        ),
+    }
+    Ok(builder.build()?)
+}
+
+/// Returns true iff the DB holds no transactions (i.e., not even the genesis transaction)
+fn is_db_empty(db: &DbReaderWriter) -> Result<bool> {
+    let tree_state = db
+        .reader
+        .get_latest_tree_state()
+        .context("Failed to read the latest tree state from the DB")?;
+    Ok(tree_state.num_transactions == 0)
+}
+
+/// Waits (for at most `timeout`) until the genesis file exists and holds a valid genesis
+/// transaction. This lets an empty node start before its genesis file is delivered.
+fn wait_for_genesis_file(genesis_file: &Path, timeout: Duration) -> Result<Transaction> {
+    if genesis_file.as_os_str().is_empty() {
+        bail!("The DB is empty and no genesis transaction was provided!");
+    }
+
+    let start_time = Instant::now();
+    if !timeout.is_zero() {
+        info!(
+            "The DB is empty, waiting up to {} secs for the genesis file {:?}",
+            timeout.as_secs(),
+            genesis_file
+        );
+    }
+    loop {
+        // The file may be partially written when first seen, so read errors are retried
+        let last_error = match std::fs::read(genesis_file) {
+            Ok(bytes) => match bcs::from_bytes::<Transaction>(&bytes) {
+                Ok(genesis) => return Ok(genesis),
+                Err(error) => anyhow!(error),
+            },
+            Err(error) => anyhow!(error),
+        };
+        if start_time.elapsed() >= timeout {
+            return Err(last_error).with_context(|| {
+                format!(
+                    "The DB is empty and no genesis transaction was provided (waited {} secs \
+                    for the genesis file {:?})",
+                    timeout.as_secs(),
+                    genesis_file
+                )
+            });
+        }
+        thread::sleep(GENESIS_POLL_INTERVAL);
+    }
+}
+
+/// Returns the age of the DB's latest ledger info iff the DB is already at (or beyond) the
+/// waypoint and that ledger info is at most `MAX_SYNCED_LEDGER_INFO_AGE` old, i.e., iff
+/// consensus can start without waiting for state sync.
+///
+/// The age check deliberately stands in for checking that the ledger info is from the
+/// current epoch: before state sync has reached any peers, the node has no way of knowing
+/// the network's current epoch. As epochs last far longer than the maximum age, a recent
+/// ledger info is almost certainly from the current epoch. In turn, a node that was down
+/// for longer than that always waits for state sync, even if no reconfiguration happened.
+/// This is deliberately conservative: any failure to read the ledger info means the node
+/// is treated as needing to sync.
+fn synced_ledger_info_age(db: &DbReaderWriter, waypoint: Waypoint) -> Option<Duration> {
+    let ledger_info = db.reader.get_latest_ledger_info().ok()?;
+    let ledger_info = ledger_info.ledger_info();
+    if ledger_info.version() < waypoint.version() {
+        return None;
+    }
+
+    let now_usecs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_micros() as u64;
+    let ledger_info_age =
+        Duration::from_micros(now_usecs.saturating_sub(ledger_info.timestamp_usecs()));
+    if ledger_info_age <= MAX_SYNCED_LEDGER_INFO_AGE {
+        Some(ledger_info_age)
+    } else {
+        None
+    }
+}
+
+/// Removes the networks disabled by the operator from the given network configs. The
+/// validator network can't be disabled on a validator.
+fn remove_disabled_networks<'a>(
+    network_configs: Vec<&'a NetworkConfig>,
+    disabled_network_ids: &[NetworkId],
+    role: RoleType,
+) -> Result<Vec<&'a NetworkConfig>> {
+    if role.is_validator()
+        && disabled_network_ids
+            .iter()
+            .any(|network_id| network_id.is_validator_network())
+    {
+        bail!("The validator network can't be disabled on a validator!");
+    }
+
+    let (disabled_network_configs, network_configs): (Vec<_>, Vec<_>) = network_configs
+        .into_iter()
+        .partition(|network_config| disabled_network_ids.contains(&network_config.network_id));
+    for network_config in disabled_network_configs {
+        warn!(
+            "Network {} is disabled and will not be started!",
+            network_config.network_id
+        );
+    }
+    Ok(network_configs)
+}
+
+/// Returns the networks on which the storage service should be served. If no networks
+/// are selected, the storage service is served on every configured network.
+fn get_storage_service_networks(
+    network_ids: &[NetworkId],
+    selected_network_ids: Option<&[NetworkId]>,
+) -> Result<HashSet<NetworkId>> {
+    let selected_network_ids = match selected_network_ids {
+        Some(selected_network_ids) => selected_network_ids,
+        None => return Ok(network_ids.iter().cloned().collect()),
+    };
+
+    for network_id in selected_network_ids {
+        if !network_ids.contains(network_id) {
+            bail!(
+                "The storage service can't be served on network {}, it isn't configured!",
+                network_id
+            );
+        }
+    }
+    Ok(selected_network_ids.iter().cloned().collect())
+}
+
+/// Returns the number of currently connected peers across all networks
+fn count_connected_peers(peer_metadata_storage: &PeerMetadataStorage) -> usize {
+    count_connected_peers_per_network(peer_metadata_storage)
+        .into_iter()
+        .map(|(_, connected_peers)| connected_peers)
+        .sum()
+}
+
+/// Returns the number of currently connected peers on each network
+fn count_connected_peers_per_network(
+    peer_metadata_storage: &PeerMetadataStorage,
+) -> Vec<(NetworkId, usize)> {
+    peer_metadata_storage
+        .networks()
+        .map(|network_id| {
+            let connected_peers = peer_metadata_storage
+                .read_filtered(network_id, |(_, peer_info)| peer_info.is_connected())
+                .len();
+            (network_id, connected_peers)
+        })
+        .collect()
+}
+
+/// Periodically refreshes the rocksdb properties of the given DB (which back the storage
+/// gauges, e.g., the SST file sizes of each column family).
+async fn periodic_rocksdb_properties_refresh(aptos_db: Arc<AptosDB>, refresh_interval: Duration) {
+    let mut refresh_interval = IntervalStream::new(tokio::time::interval(refresh_interval));
+    while refresh_interval.next().await.is_some() {
+        let aptos_db = Arc::clone(&aptos_db);
+        match tokio::task::spawn_blocking(move || aptos_db.update_rocksdb_properties()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => debug!("Failed to refresh the rocksdb properties: {}", error),
+            Err(error) => debug!("The rocksdb properties refresh task failed: {}", error),
+        }
+    }
+}
+
+/// Waits until the node has synced to within `threshold_versions` of the highest version
+/// advertised by its peers, and records the time this took since the node started.
+async fn monitor_time_to_sync(
+    node_context: Arc<NodeContext>,
+    threshold_versions: u64,
+    aptos_data_client: AptosNetDataClient,
+) {
+    let mut check_interval =
+        IntervalStream::new(tokio::time::interval(TIME_TO_SYNC_CHECK_INTERVAL));
+    while check_interval.next().await.is_some() {
+        let highest_advertised_version = match aptos_data_client
+            .get_global_data_summary()
+            .advertised_data
+            .highest_synced_ledger_info()
+        {
+            Some(ledger_info) => ledger_info.ledger_info().version(),
+            None => continue, // We don't know of any peer data yet
+        };
+        let synced_version = match node_context.synced_version() {
+            Some(synced_version) => synced_version,
+            None => continue,
+        };
+
+        if synced_version.saturating_add(threshold_versions) >= highest_advertised_version {
+            let time_to_sync_secs = node_context.start_time().elapsed().as_secs();
+            let _ = TIME_TO_SYNC_SECS.set(time_to_sync_secs);
+            metrics::TIME_TO_SYNC_SECONDS.set(time_to_sync_secs as i64);
+            info!(
+                time_to_sync_secs = time_to_sync_secs,
+                synced_version = synced_version,
+                highest_advertised_version = highest_advertised_version,
+                "Node synced to within {} versions of the highest advertised version",
+                threshold_versions
+            );
+            return;
+        }
+    }
+}
+
+pub fn setup_environment(
+    node_config: &NodeConfig,
+    options: &NodeOptions,
+    logger: Option<Arc<Logger>>,
+) -> Result<AptosHandle> {
+    let start_time = options.start_time.unwrap_or_else(Instant::now);
+    validation::validate_network_configs(node_config)?;
+    validation::check_listen_address_conflicts(node_config, options)
+        .context("Invalid listen addresses in the node config")?;
+    options
+        .validate_channel_sizes()
+        .context("Invalid channel sizes")?;
+    info!(
+        consensus_to_mempool_channel_size = options.consensus_to_mempool_channel_size,
+        mempool_client_channel_size = options.mempool_client_channel_size,
+        mempool_network_channel_size = options.mempool_network_channel_size,
+        "Effective internal channel sizes"
+    );
+
+    let debug_if = setup_debug_interface(node_config, options, logger)
+        .context("Failed to start the debug interface")?;
+
+    let metric_servers = start_metric_servers(node_config, options)?;
 
-    let mut instant = Instant::now();
+    if options.reader_mode {
+        return setup_reader_environment(node_config, options, debug_if, metric_servers);
+    }
+
+    let mut startup_timer = StartupTimer::new(start_time);
+    let db_path = node_config.storage.dir();
     let (aptos_db, db_rw) = DbReaderWriter::wrap(
         AptosDB::open(
-            &node_config.storage.dir(),
+            &db_path,
             false, /* readonly */
             node_config.storage.storage_pruner_config,
             node_config.storage.rocksdb_config,
         )
-        .expect("DB should open."),
-    );
-    let _simple_storage_service = start_storage_service_with_db(node_config, Arc::clone(&aptos_db));
-    let backup_service = start_backup_service(
-        node_config.storage.backup_service_address,
-        Arc::clone(&aptos_db),
+        .map_err(|error| {
+            let description = db_errors::describe_db_open_error(&db_path, &error);
+            error.context(description)
+        })?,
     );
+    if let Some(verification_mode) = options.db_verification_mode {
+        db_verification::verify_db(&db_rw.reader, verification_mode)
+            .context("DB verification failed, refusing to start the node")?;
+    }
+
+    let legacy_storage_service = if options.disable_legacy_storage_service {
+        info!("The legacy storage service is disabled");
+        None
+    } else {
+        info!("Starting the legacy storage service");
+        Some(start_storage_service_with_db(
+            node_config,
+            Arc::clone(&aptos_db),
+        ))
+    };
+    let backup_service = start_backup(node_config, options, Arc::clone(&aptos_db))?;
+
+    // Periodically refresh the rocksdb properties so that the storage metrics stay current
+    if options.rocksdb_properties_refresh_interval.is_zero() {
+        info!("Periodic rocksdb property refreshes are disabled");
+    } else {
+        debug_if
+            .runtime()
+            .handle()
+            .spawn(periodic_rocksdb_properties_refresh(
+                Arc::clone(&aptos_db),
+                options.rocksdb_properties_refresh_interval,
+            ));
+    }
+
+    startup_timer.finish_stage("storage");
 
     let genesis_waypoint = node_config.base.waypoint.genesis_waypoint();
     // if there's genesis txn and waypoint, commit it if the result matches.
     if let Some(genesis) = get_genesis_txn(node_config) {
         maybe_bootstrap::<AptosVM>(&db_rw, genesis, genesis_waypoint)
-            .expect("Db-bootstrapper should not fail.");
+            .context("Failed to bootstrap the DB with the genesis transaction")?;
+    } else if is_db_empty(&db_rw)? {
+        let genesis = wait_for_genesis_file(
+            &node_config.execution.genesis_file_location,
+            options.genesis_wait,
+        )?;
+        maybe_bootstrap::<AptosVM>(&db_rw, &genesis, genesis_waypoint)
+            .context("Failed to bootstrap the DB with the genesis transaction")?;
     } else {
         info!("Genesis txn not provided, it's fine if you don't expect to apply it otherwise please double check config");
     }
+    db_verification::check_waypoint_consistency(
+        &db_rw.reader,
+        node_config.base.waypoint.waypoint(),
+    )
+    .context("The configured waypoint conflicts with the DB, refusing to start the node")?;
+    startup_timer.finish_stage("genesis_bootstrap");
     AptosVM::set_concurrency_level_once(node_config.execution.concurrency_level as usize);
 
-    debug!(
-        "Storage service started in {} ms",
-        instant.elapsed().as_millis()
-    );
-
-    let chain_id = fetch_chain_id(&db_rw);
+    let chain_id = fetch_chain_id(&db_rw).context("Failed to read the chain ID from the DB")?;
+    set_node_info_metric(chain_id, node_config.base.role);
     let mut network_runtimes = vec![];
     let mut state_sync_network_handles = vec![];
     let mut mempool_network_handles = vec![];
@@ -532,48 +1367,83 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
     );
     let mempool_reconfig_subscription = event_subscription_service
         .subscribe_to_reconfigurations()
-        .unwrap();
+        .context("Failed to subscribe mempool to reconfigurations")?;
 
     // Create a consensus subscription for reconfiguration events (if this node is a validator).
     let consensus_reconfig_subscription = if node_config.base.role.is_validator() {
         Some(
             event_subscription_service
                 .subscribe_to_reconfigurations()
-                .unwrap(),
+                .context("Failed to subscribe consensus to reconfigurations")?,
         )
     } else {
         None
     };
 
+    // Monitor whether this node is in the on-chain validator set (if it's a validator)
+    if let Some(validator_network) = node_config.validator_network.as_ref() {
+        if node_config.base.role.is_validator() {
+            let validator_set_reconfig_subscription = event_subscription_service
+                .subscribe_to_reconfigurations()
+                .context("Failed to subscribe the validator set monitor to reconfigurations")?;
+            debug_if.runtime().handle().spawn(
+                validator_set_monitor::monitor_validator_set_membership(
+                    validator_network.peer_id(),
+                    validator_set_reconfig_subscription,
+                ),
+            );
+        }
+    }
+
     // Gather all network configs into a single vector.
     let mut network_configs: Vec<&NetworkConfig> = node_config.full_node_networks.iter().collect();
     if let Some(network_config) = node_config.validator_network.as_ref() {
         network_configs.push(network_config);
     }
+    let network_configs = remove_disabled_networks(
+        network_configs,
+        &options.disabled_networks,
+        node_config.base.role,
+    )
+    .context("Unable to disable the requested networks")?;
 
     // Instantiate every network and collect the requisite endpoints for state_sync, mempool, and consensus.
-    let mut network_ids = HashSet::new();
-    network_configs.iter().for_each(|config| {
-        let network_id = config.network_id;
-        // Guarantee there is only one of this network
-        if network_ids.contains(&network_id) {
-            panic!(
-                "Duplicate NetworkId: '{}'.  Can't start node with duplicate networks",
-                network_id
-            );
-        }
-        network_ids.insert(network_id);
-    });
-    let network_ids: Vec<_> = network_ids.into_iter().collect();
+    // (The network ids were already validated to be unique.)
+    let network_ids: Vec<_> = network_configs
+        .iter()
+        .map(|config| config.network_id)
+        .collect();
+
+    // Determine the networks on which we serve the storage service
+    let storage_service_networks =
+        get_storage_service_networks(&network_ids, options.storage_service_networks.as_deref())
+            .context("Invalid storage service network selection")?;
+    info!(
+        "Serving the AptosNet storage service on networks: {:?}",
+        storage_service_networks
+    );
+    if storage_service_networks.is_empty() && options.disable_legacy_storage_service {
+        warn!("Both the legacy and the AptosNet storage services are disabled! Peers will be unable to sync from this node.");
+    }
 
     let peer_metadata_storage = PeerMetadataStorage::new(&network_ids);
+    startup_timer.start_stage();
     for network_config in network_configs.into_iter() {
         debug!("Creating runtime for {}", network_config.network_id);
-        let runtime = Builder::new_multi_thread()
-            .thread_name(format!("network-{}", network_config.network_id))
-            .enable_all()
-            .build()
-            .expect("Failed to start runtime. Won't be able to start networking.");
+        let worker_threads = options
+            .network_runtime_worker_threads
+            .get(&network_config.network_id)
+            .cloned();
+        let runtime = create_runtime(
+            format!("network-{}", network_config.network_id),
+            worker_threads,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to create the runtime for the {} network",
+                network_config.network_id
+            )
+        })?;
 
         // Entering here gives us a runtime to instantiate all the pieces of the builder
         let _enter = runtime.enter();
@@ -594,16 +1464,15 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
             network_builder.add_p2p_service(&state_sync_v1_network_config());
         state_sync_network_handles.push((network_id, state_sync_sender, state_sync_events));
 
-        // TODO(philiphayes): configure which networks we serve the storage service
-        // on? for example, if we're a light node we wouldn't want to provide the
-        // storage service at all.
-
-        // Register the network-facing storage service with Network.
-        let storage_service_events =
-            network_builder.add_service(&storage_service_server::network::network_endpoint_config(
-                node_config.state_sync.storage_service,
-            ));
-        storage_service_server_network_handles.push(storage_service_events);
+        // Register the network-facing storage service with Network (if this network serves it).
+        if storage_service_networks.contains(&network_id) {
+            let storage_service_events = network_builder.add_service(
+                &storage_service_server::network::network_endpoint_config(
+                    node_config.state_sync.storage_service,
+                ),
+            );
+            storage_service_server_network_handles.push(storage_service_events);
+        }
 
         // Register the storage-service clients with Network
         let storage_service_sender =
@@ -612,18 +1481,13 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
 
         // Create the endpoints to connect the Network to mempool.
         let (mempool_sender, mempool_events) = network_builder.add_p2p_service(
-            &aptos_mempool::network::network_endpoint_config(MEMPOOL_NETWORK_CHANNEL_BUFFER_SIZE),
+            &aptos_mempool::network::network_endpoint_config(options.mempool_network_channel_size),
         );
         mempool_network_handles.push((network_id, mempool_sender, mempool_events));
 
         // Perform steps relevant specifically to Validator networks.
         if network_id.is_validator_network() {
-            // A valid config is allowed to have at most one ValidatorNetwork
-            // TODO:  `expect_none` would be perfect here, once it is stable.
-            if consensus_network_handles.is_some() {
-                panic!("There can be at most one validator network!");
-            }
-
+            // A valid config has at most one ValidatorNetwork (see validate_network_configs)
             consensus_network_handles = Some(
                 network_builder
                     .add_p2p_service(&consensus::network_interface::network_endpoint_config()),
@@ -636,6 +1500,25 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
         debug!("Network built for network context: {}", network_context);
         network_runtimes.push(runtime);
     }
+    startup_timer.finish_stage("networks");
+
+    // Build the context shared by the background tasks
+    let node_context = NodeContext::new(
+        node_config,
+        chain_id,
+        db_rw.reader.clone(),
+        peer_metadata_storage.clone(),
+        start_time,
+    );
+    let health_checker = start_health_checker(&debug_if, options, node_context.clone(), true)?;
+
+    // Keep track of connection churn across all networks
+    let connection_churn_monitor =
+        ConnectionChurnMonitor::new(node_context.peer_metadata_storage().clone());
+    debug_if
+        .runtime()
+        .handle()
+        .spawn(connection_churn_monitor.clone().start());
 
     // TODO set up on-chain discovery network based on UpstreamConfig.fallback_network
     // and pass network handles to mempool/state sync
@@ -648,8 +1531,16 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
             node_config.state_sync.client_commit_timeout_ms,
         );
 
+    // Create the chunk executor. A single instance is shared by everything that applies
+    // chunks to storage, so that its caches aren't duplicated over the same DB.
+    let chunk_executor = Arc::new(
+        ChunkExecutor::<AptosVM>::new(db_rw.clone())
+            .context("Failed to create the chunk executor")?,
+    );
+
     // Create the state sync runtimes
-    let state_sync_runtimes = create_state_sync_runtimes(
+    startup_timer.start_stage();
+    let (mut state_sync_runtimes, aptos_data_client) = create_state_sync_runtimes(
         node_config,
         storage_service_server_network_handles,
         storage_service_client_network_handles,
@@ -660,16 +1551,30 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
         genesis_waypoint,
         event_subscription_service,
         db_rw.clone(),
-    );
+        chunk_executor,
+        options.state_sync_runtime_worker_threads,
+    )
+    .context("Failed to start state sync")?;
+    startup_timer.finish_stage("state_sync_runtimes");
 
-    let (mp_client_sender, mp_client_events) = channel(AC_SMP_CHANNEL_BUFFER_SIZE);
+    let (mp_client_sender, mp_client_events) = channel(options.mempool_client_channel_size);
 
-    let api_runtime = bootstrap_api(node_config, chain_id, aptos_db, mp_client_sender).unwrap();
+    // The mempool client channel is wired even if the API is disabled, as other clients
+    // (e.g., AptosHandle::submit_transaction) may still submit transactions.
+    let api_runtime = start_api(
+        node_config,
+        chain_id,
+        aptos_db,
+        mp_client_sender.clone(),
+        options.disable_api,
+    )?;
+    startup_timer.finish_stage("api");
 
     let mut consensus_runtime = None;
-    let (consensus_to_mempool_sender, consensus_requests) = channel(INTRA_NODE_CHANNEL_BUFFER_SIZE);
+    let (consensus_to_mempool_sender, consensus_requests) =
+        channel(options.consensus_to_mempool_channel_size);
 
-    instant = Instant::now();
+    startup_timer.start_stage();
     let mempool = aptos_mempool::bootstrap(
         node_config,
         Arc::clone(&db_rw.reader),
@@ -680,61 +1585,746 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
         mempool_reconfig_subscription,
         peer_metadata_storage.clone(),
     );
-    debug!("Mempool started in {} ms", instant.elapsed().as_millis());
+    startup_timer.finish_stage("mempool");
 
     // StateSync should be instantiated and started before Consensus to avoid a cyclic dependency:
     // network provider -> consensus -> state synchronizer -> network provider.  This has resulted
     // in a deadlock as observed in GitHub issue #749.
-    if let Some((consensus_network_sender, consensus_network_events)) = consensus_network_handles {
-        // Make sure that state synchronizer is caught up at least to its waypoint
-        // (in case it's present). There is no sense to start consensus prior to that.
-        // TODO: Note that we need the networking layer to be able to discover & connect to the
-        // peers with potentially outdated network identity public keys.
-        debug!("Wait until state sync is initialized");
-        state_sync_runtimes.block_until_initialized();
-        debug!("State sync initialization complete.");
-
-        // Initialize and start consensus.
-        instant = Instant::now();
-        consensus_runtime = Some(start_consensus(
-            node_config,
-            consensus_network_sender,
-            consensus_network_events,
-            Arc::new(consensus_notifier),
+    let mut pending_consensus = None;
+    if let Some((network_sender, network_events)) = consensus_network_handles {
+        let consensus = PendingConsensus {
+            node_config: node_config.clone(),
+            network_sender,
+            network_events,
+            consensus_notifier,
             consensus_to_mempool_sender,
-            db_rw.clone(),
-            consensus_reconfig_subscription
-                .expect("Consensus requires a reconfiguration subscription!"),
+            db_rw: db_rw.clone(),
+            reconfig_subscription: consensus_reconfig_subscription.ok_or_else(|| {
+                anyhow!(
+                    "A validator network is configured, but the node isn't a validator. \
+                    Consensus requires a reconfiguration subscription!"
+                )
+            })?,
             peer_metadata_storage,
-        ));
-        debug!("Consensus started in {} ms", instant.elapsed().as_millis());
+            waypoint: genesis_waypoint,
+            always_wait_for_state_sync: options.always_wait_for_state_sync,
+            health_checker: health_checker.clone(),
+            term: options.term.clone(),
+        };
+        if options.delay_consensus_start {
+            info!(
+                "The consensus start is delayed, consensus won't run until it's explicitly started"
+            );
+            pending_consensus = Some(consensus);
+        } else {
+            startup_timer.start_stage();
+            let (runtimes, waited_for_state_sync) = consensus.wait_for_state_sync(
+                state_sync_runtimes,
+                options.state_sync_initialization_timeout,
+            )?;
+            state_sync_runtimes = runtimes;
+            if waited_for_state_sync {
+                startup_timer.finish_stage("state_sync_initialization");
+            }
+            startup_timer.set_state_sync_initialized();
+
+            // Initialize and start consensus.
+            startup_timer.start_stage();
+            consensus_runtime = Some(consensus.start());
+            startup_timer.finish_stage("consensus");
+        }
     }
 
     // Spawn a task which will periodically dump some interesting state
-    debug_if
-        .runtime()
-        .handle()
-        .spawn(periodic_state_dump(node_config.to_owned(), db_rw.clone()));
+    debug_if.runtime().handle().spawn(periodic_state_dump(
+        node_context.clone(),
+        connection_churn_monitor,
+        options.config_dump_interval,
+        options.ledger_dump_interval,
+    ));
+
+    // Spawn a task which will record how long it took the node to sync to the chain tip
+    debug_if.runtime().handle().spawn(monitor_time_to_sync(
+        node_context.clone(),
+        options.time_to_sync_threshold_versions,
+        aptos_data_client.clone(),
+    ));
+
+    let telemetry_runtime =
+        start_telemetry_runtime(options, node_context.clone(), Some(aptos_data_client))?;
+
+    // Nodes that don't run consensus don't wait for state sync at startup, so wait for it in
+    // the background (for the node to become ready)
+    let (state_sync_runtimes, initializing_state_sync) =
+        if consensus_runtime.is_none() && pending_consensus.is_none() {
+            let health_checker = health_checker.clone();
+            let initializing_state_sync =
+                spawn_state_sync_initialization_wait(state_sync_runtimes, move || {
+                    log_state_sync_initialized(start_time);
+                    if let Some(health_checker) = health_checker {
+                        health_checker.set_state_sync_initialized();
+                    }
+                })?;
+            (None, Some(initializing_state_sync))
+        } else {
+            (Some(state_sync_runtimes), None)
+        };
+    if let Some(health_checker) = health_checker {
+        health_checker.set_started(consensus_runtime.is_some());
+    }
+    startup_timer.log_summary();
+
+    Ok(AptosHandle {
+        api: api_runtime,
+        backup: backup_service,
+        consensus_runtime,
+        _legacy_storage_service: legacy_storage_service,
+        debug: Some(debug_if),
+        mempool: Some(mempool),
+        metric_servers,
+        mp_client_sender: Some(mp_client_sender),
+        network_runtimes,
+        node_context,
+        pending_consensus,
+        state_sync_runtimes,
+        initializing_state_sync,
+        telemetry_runtime,
+    })
+}
+
+/// Sets up a reader node, which serves the REST API and the (legacy) storage service off an
+/// existing DB opened in read-only mode. No genesis is applied, and no networks, state sync,
+/// mempool, consensus or other DB writers are started.
+fn setup_reader_environment(
+    node_config: &NodeConfig,
+    options: &NodeOptions,
+    debug_if: NodeDebugService,
+    metric_servers: Vec<Runtime>,
+) -> Result<AptosHandle> {
+    let db_path = node_config.storage.dir();
+    if !db_path.exists() {
+        bail!(
+            "Unable to start the reader node, the DB directory {:?} doesn't exist!",
+            db_path
+        );
+    }
+    info!("Starting a reader node over the DB at {:?}", db_path);
 
-    let telemery_runtime = Builder::new_multi_thread()
+    let (aptos_db, db_rw) = DbReaderWriter::wrap(
+        AptosDB::open(
+            &db_path,
+            true, /* readonly */
+            node_config.storage.storage_pruner_config,
+            node_config.storage.rocksdb_config,
+        )
+        .map_err(|error| {
+            let description = db_errors::describe_db_open_error(&db_path, &error);
+            error.context(format!("{} (opened read-only)", description))
+        })?,
+    );
+    let chain_id = fetch_chain_id(&db_rw).context("Failed to read the chain ID from the DB")?;
+    set_node_info_metric(chain_id, node_config.base.role);
+
+    // The reader never accepts transactions, so nothing consumes the API's mempool requests
+    let (mp_client_sender, _) = channel(options.mempool_client_channel_size);
+    let api_runtime = start_api(
+        node_config,
+        chain_id,
+        Arc::clone(&aptos_db),
+        mp_client_sender,
+        options.disable_api,
+    )?;
+
+    let legacy_storage_service = if options.disable_legacy_storage_service {
+        info!("The legacy storage service is disabled");
+        None
+    } else {
+        info!("Starting the legacy storage service over the read-only DB");
+        Some(start_storage_service_with_db(node_config, aptos_db))
+    };
+
+    // Build the context shared by the background tasks
+    let node_context = NodeContext::new(
+        node_config,
+        chain_id,
+        db_rw.reader.clone(),
+        PeerMetadataStorage::new(&[]),
+        Instant::now(),
+    );
+    let health_checker = start_health_checker(&debug_if, options, node_context.clone(), false)?;
+
+    // Spawn a task which will periodically dump some interesting state
+    let connection_churn_monitor =
+        ConnectionChurnMonitor::new(node_context.peer_metadata_storage().clone());
+    debug_if.runtime().handle().spawn(periodic_state_dump(
+        node_context.clone(),
+        connection_churn_monitor,
+        options.config_dump_interval,
+        options.ledger_dump_interval,
+    ));
+
+    let telemetry_runtime = start_telemetry_runtime(options, node_context.clone(), None)?;
+    if let Some(health_checker) = health_checker {
+        health_checker.set_started(false);
+    }
+
+    Ok(AptosHandle {
+        api: api_runtime,
+        backup: None,
+        consensus_runtime: None,
+        _legacy_storage_service: legacy_storage_service,
+        debug: Some(debug_if),
+        mempool: None,
+        metric_servers,
+        mp_client_sender: None,
+        network_runtimes: vec![],
+        node_context,
+        pending_consensus: None,
+        state_sync_runtimes: None,
+        initializing_state_sync: None,
+        telemetry_runtime,
+    })
+}
+
+/// Starts the (internal and public) metric servers that aren't disabled, returning the
+/// runtimes they run on.
+fn start_metric_servers(node_config: &NodeConfig, options: &NodeOptions) -> Result<Vec<Runtime>> {
+    let debug_interface = &node_config.debug_interface;
+    let metric_servers = [
+        (
+            debug_interface.metrics_server_port,
+            false,
+            options.disable_metrics_server,
+        ),
+        (
+            debug_interface.public_metrics_server_port,
+            true,
+            options.disable_public_metrics_server,
+        ),
+    ];
+
+    let mut metric_server_runtimes = vec![];
+    for (port, public_metrics, disabled) in metric_servers {
+        if disabled {
+            info!("The {} is disabled", metric_server_name(public_metrics));
+            continue;
+        }
+        metric_server_runtimes.extend(start_metric_server(
+            debug_interface.address.clone(),
+            port,
+            public_metrics,
+            options.require_metrics_server,
+        )?);
+    }
+    Ok(metric_server_runtimes)
+}
+
+fn metric_server_name(public_metrics: bool) -> &'static str {
+    if public_metrics {
+        "public metric server"
+    } else {
+        "metric server"
+    }
+}
+
+/// Starts a (public or private) metric server on its own runtime, bound to the first
+/// bindable address the host resolves to. A bind failure is only fatal if the metric servers
+/// are required. Otherwise, it's logged and counted, and no runtime is returned.
+fn start_metric_server(
+    host: String,
+    port: u16,
+    public_metrics: bool,
+    required: bool,
+) -> Result<Option<Runtime>> {
+    let server_name = metric_server_name(public_metrics);
+    let thread_name = if public_metrics {
+        "public-metrics"
+    } else {
+        "metrics"
+    };
+    let started = validation::resolve_bindable_address(&host, port).and_then(|address| {
+        let runtime = create_runtime(thread_name.into(), Some(1))?;
+        metric_server::start_metric_server(runtime.handle(), address, public_metrics)?;
+        Ok(runtime)
+    });
+    match started {
+        Ok(runtime) => Ok(Some(runtime)),
+        Err(error) => {
+            if required {
+                return Err(error).with_context(|| format!("Unable to start the {}", server_name));
+            }
+            metrics::METRIC_SERVER_START_FAILURES
+                .with_label_values(&[server_name])
+                .inc();
+            error!(
+                "Unable to start the {}, its metrics won't be served: {:?}",
+                server_name, error
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Starts the backup service (unless disabled). The address is checked up front, so that
+/// an address that's already in use results in a readable error instead of a panic.
+fn start_backup(
+    node_config: &NodeConfig,
+    options: &NodeOptions,
+    aptos_db: Arc<AptosDB>,
+) -> Result<Option<Runtime>> {
+    let backup_service_address = node_config.storage.backup_service_address;
+    if options.disable_backup_service {
+        info!(
+            "The backup service is disabled, backups can't be taken from this node (nothing \
+            will be served on {})",
+            backup_service_address
+        );
+        return Ok(None);
+    }
+
+    validation::check_backup_service_address(
+        backup_service_address,
+        options.allow_public_backup_service,
+    )
+    .context("Refusing to start the backup service")?;
+    validation::check_address_bindable(
+        &backup_service_address.ip().to_string(),
+        backup_service_address.port(),
+    )
+    .context("Failed to start the backup service")?;
+    Ok(Some(start_backup_service(backup_service_address, aptos_db)))
+}
+
+/// Starts the REST API (unless disabled). The API address is checked up front, so that an
+/// address that's already in use results in a readable error.
+fn start_api(
+    node_config: &NodeConfig,
+    chain_id: ChainId,
+    aptos_db: Arc<AptosDB>,
+    mp_client_sender: MempoolClientSender,
+    disable_api: bool,
+) -> Result<Option<Runtime>> {
+    let api_address = node_config.api.address;
+    if disable_api {
+        info!(
+            "The API is disabled, nothing will be served on {}",
+            api_address
+        );
+        return Ok(None);
+    }
+
+    validation::check_address_bindable(&api_address.ip().to_string(), api_address.port())
+        .context("Failed to start the API")?;
+    let api_runtime = bootstrap_api(node_config, chain_id, aptos_db, mp_client_sender)
+        .context("Failed to start the API")?;
+    Ok(Some(api_runtime))
+}
+
+/// Starts the health server on the debug interface's runtime (if a health check address
+/// is configured), and returns the health checker to notify once the node has started.
+fn start_health_checker(
+    debug_if: &NodeDebugService,
+    options: &NodeOptions,
+    node_context: Arc<NodeContext>,
+    require_state_sync_initialized: bool,
+) -> Result<Option<Arc<HealthChecker>>> {
+    let address = match options.health_check_address {
+        Some(address) => address,
+        None => return Ok(None),
+    };
+    let health_checker = HealthChecker::new(node_context, require_state_sync_initialized);
+    health::start_health_server(debug_if.runtime().handle(), address, health_checker.clone())?;
+    Ok(Some(health_checker))
+}
+
+/// Returns true iff telemetry is disabled, either by the node options or by the
+/// environment variable (whose value is given)
+fn is_telemetry_disabled(options: &NodeOptions, env_var_value: Option<&str>) -> bool {
+    let disabled_by_env_var = env_var_value.map_or(false, |value| {
+        let value = value.trim();
+        !(value == "0" || value.eq_ignore_ascii_case("false"))
+    });
+    options.disable_telemetry || options.telemetry_push_interval.is_zero() || disabled_by_env_var
+}
+
+/// Creates the telemetry runtime and spawns the periodic telemetry dump on it, unless
+/// telemetry is disabled (in which case nothing is started). Without a data client (e.g.,
+/// in reader mode), the version lag isn't reported.
+fn start_telemetry_runtime(
+    options: &NodeOptions,
+    node_context: Arc<NodeContext>,
+    aptos_data_client: Option<AptosNetDataClient>,
+) -> Result<Option<Runtime>> {
+    let env_var_value = std::env::var(DISABLE_TELEMETRY_ENV_VAR).ok();
+    if is_telemetry_disabled(options, env_var_value.as_deref()) {
+        info!("Telemetry is disabled, no data will be pushed to the telemetry service");
+        return Ok(None);
+    }
+    let push_interval = options.telemetry_push_interval;
+    info!(
+        "Telemetry is enabled, pushing {} events every {} secs (+/- {}%, set {} to disable)",
+        APTOS_NODE_PUSH_METRICS,
+        push_interval.as_secs(),
+        TELEMETRY_PUSH_JITTER * 100.0,
+        DISABLE_TELEMETRY_ENV_VAR
+    );
+
+    let telemetry_runtime = Builder::new_multi_thread()
         .thread_name("aptos-telemetry")
         .enable_all()
         .build()
-        .expect("Failed to create aptos telemetry runtime!");
+        .context("Failed to create the aptos telemetry runtime")?;
 
-    telemery_runtime
-        .handle()
-        .spawn(periodic_telemetry_dump(node_config.to_owned(), db_rw));
+    telemetry_runtime.handle().spawn(periodic_telemetry_dump(
+        node_context,
+        push_interval,
+        aptos_data_client,
+    ));
 
-    AptosHandle {
-        _api: api_runtime,
-        _backup: backup_service,
-        _consensus_runtime: consensus_runtime,
-        _debug: debug_if,
-        _mempool: mempool,
-        _network_runtimes: network_runtimes,
-        _state_sync_runtimes: state_sync_runtimes,
-        _telemetry_runtime: telemery_runtime,
-    }
+    Ok(Some(telemetry_runtime))
 }
 // let config_path = config_path.canonicalize().unwrap();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::{rngs::StdRng, SeedableRng};
+    use aptos_config::config::WaypointConfig;
+    use aptos_temppath::TempPath;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_disabled_api_binds_nothing() {
+        // Pick a free port for the API
+        let api_address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut node_config = NodeConfig::default();
+        node_config.api.address = api_address;
+
+        let db_dir = TempPath::new();
+        let aptos_db = Arc::new(AptosDB::new_for_test(&db_dir));
+        let (mp_client_sender, _) = channel(1);
+        let api_runtime = start_api(
+            &node_config,
+            ChainId::test(),
+            aptos_db,
+            mp_client_sender,
+            true,
+        )
+        .unwrap();
+        assert!(api_runtime.is_none());
+
+        validation::check_address_bindable(&api_address.ip().to_string(), api_address.port())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_backup_service_address_in_use() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut node_config = NodeConfig::default();
+        node_config.storage.backup_service_address = listener.local_addr().unwrap();
+
+        let db_dir = TempPath::new();
+        let aptos_db = Arc::new(AptosDB::new_for_test(&db_dir));
+        let error = start_backup(&node_config, &NodeOptions::default(), aptos_db.clone())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Failed to start the backup service"));
+
+        // A disabled backup service doesn't bind anything, so the conflict doesn't matter
+        let options = NodeOptions {
+            disable_backup_service: true,
+            ..NodeOptions::default()
+        };
+        assert!(start_backup(&node_config, &options, aptos_db)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_metric_server_port_conflict() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Without metrics being required, the failure is only counted
+        let failures = || {
+            metrics::METRIC_SERVER_START_FAILURES
+                .with_label_values(&[metric_server_name(true)])
+                .get()
+        };
+        let failures_before = failures();
+        let metric_server = start_metric_server("127.0.0.1".into(), port, true, false).unwrap();
+        assert!(metric_server.is_none());
+        assert_eq!(failures(), failures_before + 1);
+
+        // With metrics being required, startup fails
+        let error = start_metric_server("127.0.0.1".into(), port, true, true)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Unable to start the public metric server"));
+    }
+
+    #[test]
+    fn test_wait_for_genesis_file() {
+        let error = wait_for_genesis_file(Path::new(""), Duration::from_secs(10)).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("no genesis transaction was provided"));
+
+        let genesis_dir = TempPath::new();
+        genesis_dir.create_as_dir().unwrap();
+        let genesis_file = genesis_dir.path().join("genesis.blob");
+        let error = wait_for_genesis_file(&genesis_file, Duration::ZERO).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("no genesis transaction was provided"));
+
+        // An invalid genesis file is retried until the timeout
+        std::fs::write(&genesis_file, b"not a transaction").unwrap();
+        let error = wait_for_genesis_file(&genesis_file, Duration::ZERO).unwrap_err();
+        assert!(error.to_string().contains("genesis.blob"));
+    }
+
+    #[test]
+    fn test_fetch_chain_id_from_empty_db() {
+        let db_dir = TempPath::new();
+        let db_rw = DbReaderWriter::new(AptosDB::new_for_test(&db_dir));
+        assert!(fetch_chain_id(&db_rw).is_err());
+    }
+
+    #[test]
+    fn test_rocksdb_properties_refresh() {
+        let estimated_transaction_keys = || {
+            aptosdb::metrics::ROCKSDB_PROPERTIES
+                .with_label_values(&["transaction", "aptos_rocksdb_estimate-num-keys"])
+                .get()
+        };
+        let refresh_once = |aptos_db: &Arc<AptosDB>| {
+            // The first tick is immediate, so this runs exactly one refresh
+            let runtime = Runtime::new().unwrap();
+            runtime.block_on(async {
+                let _ = tokio::time::timeout(
+                    Duration::from_secs(1),
+                    periodic_rocksdb_properties_refresh(
+                        Arc::clone(aptos_db),
+                        Duration::from_secs(3_600),
+                    ),
+                )
+                .await;
+            });
+        };
+
+        let db_dir = TempPath::new();
+        let (aptos_db, db_rw) = DbReaderWriter::wrap(AptosDB::new_for_test(&db_dir));
+        refresh_once(&aptos_db);
+        assert_eq!(estimated_transaction_keys(), 0);
+
+        // Commit the genesis transaction, which the next refresh must pick up
+        let config_dir = TempPath::new();
+        config_dir.create_as_dir().unwrap();
+        let (_, genesis, genesis_waypoint, _) =
+            aptos_genesis_tool::validator_builder::ValidatorBuilder::new(
+                config_dir.path(),
+                cached_framework_packages::module_blobs().to_vec(),
+            )
+            .build(StdRng::from_seed([0; 32]))
+            .unwrap();
+        maybe_bootstrap::<AptosVM>(&db_rw, &genesis, genesis_waypoint).unwrap();
+        refresh_once(&aptos_db);
+        assert!(estimated_transaction_keys() > 0);
+    }
+
+    #[test]
+    fn test_telemetry_mempool_txn_count() {
+        let db_dir = TempPath::new();
+        let node_context = NodeContext::new(
+            &NodeConfig::default(),
+            ChainId::test(),
+            Arc::new(AptosDB::new_for_test(&db_dir)),
+            PeerMetadataStorage::new(&[]),
+            Instant::now(),
+        );
+
+        // Mempool keeps the size of its system TTL index up to date once it's running
+        aptos_mempool::counters::CORE_MEMPOOL_INDEX_SIZE
+            .with_label_values(&[MEMPOOL_SIZE_INDEX_LABEL])
+            .set(3);
+        let metrics_params = telemetry_metrics(&node_context, None);
+        assert_eq!(
+            metrics_params
+                .get(MEMPOOL_TXN_COUNT_METRIC)
+                .map(String::as_str),
+            Some("3")
+        );
+    }
+
+    #[test]
+    fn test_telemetry_disabled() {
+        let options = NodeOptions::default();
+        assert!(!is_telemetry_disabled(&options, None));
+        assert!(!is_telemetry_disabled(&options, Some("0")));
+        assert!(!is_telemetry_disabled(&options, Some("false")));
+        assert!(is_telemetry_disabled(&options, Some("1")));
+        assert!(is_telemetry_disabled(&options, Some("")));
+        let zero_interval_options = NodeOptions {
+            telemetry_push_interval: Duration::ZERO,
+            ..NodeOptions::default()
+        };
+        assert!(is_telemetry_disabled(&zero_interval_options, None));
+
+        // A disabled telemetry doesn't start a runtime (or spawn any task)
+        let options = NodeOptions {
+            disable_telemetry: true,
+            ..NodeOptions::default()
+        };
+        assert!(is_telemetry_disabled(&options, None));
+        let mut node_config = NodeConfig::default();
+        node_config.base.waypoint = WaypointConfig::FromConfig(Waypoint::default());
+        let db_dir = TempPath::new();
+        let node_context = NodeContext::new(
+            &node_config,
+            ChainId::test(),
+            Arc::new(AptosDB::new_for_test(&db_dir)),
+            PeerMetadataStorage::new(&[]),
+            Instant::now(),
+        );
+        assert!(start_telemetry_runtime(&options, node_context, None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_connected_peers_per_network() {
+        let peer_metadata_storage = PeerMetadataStorage::new(&[NetworkId::Vfn, NetworkId::Public]);
+        let mut connected_peers = count_connected_peers_per_network(&peer_metadata_storage);
+        connected_peers.sort();
+        assert_eq!(
+            connected_peers,
+            vec![(NetworkId::Vfn, 0), (NetworkId::Public, 0)]
+        );
+        assert_eq!(count_connected_peers(&peer_metadata_storage), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_runtime_worker_threads() {
+        // Thread names are truncated to 15 bytes in /proc, so keep the name short and unique
+        let thread_name = "test-rt-workers";
+        let count_threads = || {
+            std::fs::read_dir("/proc/self/task")
+                .unwrap()
+                .filter_map(|task| std::fs::read_to_string(task.ok()?.path().join("comm")).ok())
+                .filter(|comm| comm.trim_end() == thread_name)
+                .count()
+        };
+
+        let runtime = create_runtime(thread_name.into(), Some(2)).unwrap();
+        // The workers name themselves once they're running, so give them some time
+        let start_time = Instant::now();
+        while count_threads() < 2 && start_time.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(count_threads(), 2);
+        drop(runtime);
+
+        assert!(create_runtime(thread_name.into(), Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_jittered_interval() {
+        let interval = Duration::from_secs(60);
+        let mut rng = ::rand::thread_rng();
+        for _ in 0..100 {
+            let jittered_interval = jittered_interval(interval, &mut rng);
+            assert!(jittered_interval >= Duration::from_secs(54));
+            assert!(jittered_interval <= Duration::from_secs(66));
+        }
+    }
+
+    #[test]
+    fn test_node_info_metric() {
+        let labels = |chain_id: ChainId, role: RoleType| {
+            vec![
+                GIT_SHA.unwrap_or("unknown").to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+                chain_id.id().to_string(),
+                role.as_str().to_string(),
+            ]
+        };
+        let node_info_value = |labels: &[String]| {
+            let labels: Vec<&str> = labels.iter().map(|label| label.as_str()).collect();
+            metrics::NODE_INFO.with_label_values(&labels).get()
+        };
+
+        set_node_info_metric(ChainId::test(), RoleType::Validator);
+        assert_eq!(
+            node_info_value(&labels(ChainId::test(), RoleType::Validator)),
+            1
+        );
+
+        // Updating the info replaces the previous labels
+        set_node_info_metric(ChainId::new(42), RoleType::FullNode);
+        assert_eq!(
+            node_info_value(&labels(ChainId::new(42), RoleType::FullNode)),
+            1
+        );
+        assert_eq!(
+            node_info_value(&labels(ChainId::test(), RoleType::Validator)),
+            0
+        );
+    }
+
+    #[test]
+    fn test_remove_disabled_networks() {
+        let vfn_network = NetworkConfig::network_with_id(NetworkId::Vfn);
+        let public_network = NetworkConfig::network_with_id(NetworkId::Public);
+        let network_configs = remove_disabled_networks(
+            vec![&vfn_network, &public_network],
+            &[NetworkId::Public],
+            RoleType::FullNode,
+        )
+        .unwrap();
+        assert_eq!(network_configs.len(), 1);
+        assert_eq!(network_configs[0].network_id, NetworkId::Vfn);
+    }
+
+    #[test]
+    fn test_validator_network_cannot_be_disabled_on_validator() {
+        let validator_network = NetworkConfig::network_with_id(NetworkId::Validator);
+        remove_disabled_networks(
+            vec![&validator_network],
+            &[NetworkId::Validator],
+            RoleType::Validator,
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_storage_service_networks_default_to_all() {
+        let network_ids = vec![NetworkId::Validator, NetworkId::Vfn, NetworkId::Public];
+        let storage_service_networks = get_storage_service_networks(&network_ids, None).unwrap();
+        assert_eq!(
+            storage_service_networks,
+            network_ids.into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    #[test]
+    fn test_storage_service_networks_public_only() {
+        let network_ids = vec![NetworkId::Vfn, NetworkId::Public];
+        let storage_service_networks =
+            get_storage_service_networks(&network_ids, Some(&[NetworkId::Public])).unwrap();
+        assert_eq!(
+            storage_service_networks,
+            vec![NetworkId::Public].into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    #[test]
+    fn test_storage_service_networks_unknown_network() {
+        let network_ids = vec![NetworkId::Public];
+        get_storage_service_networks(&network_ids, Some(&[NetworkId::Validator])).unwrap_err();
+    }
+}
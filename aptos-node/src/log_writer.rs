@@ -0,0 +1,178 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! A log file printer with size based rotation, so that the log file of a long running node
+//! doesn't grow unbounded.
+
+use aptos_infallible::Mutex;
+use aptos_logger::Writer;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+struct LogFile {
+    file: File,
+    size_bytes: u64,
+    // After a failed rotation, the size at which the rotation is retried
+    rotation_retry_bytes: Option<u64>,
+}
+
+/// Writes logs to a file, rotating it once it would exceed `max_bytes`. On rotation, the
+/// file is renamed to `<file>.1` (shifting existing `<file>.<n>` to `<file>.<n + 1>`), and
+/// at most `max_files` rotated files are kept. Writes and rotations are serialized, so
+/// lines are never interleaved or lost across a rotation. If a rotation fails, the failure
+/// is reported once, and the rotation is only retried after another `max_bytes` are written.
+pub struct RotatingFileWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    log_file: Mutex<LogFile>,
+}
+
+impl RotatingFileWriter {
+    pub fn new(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let log_file = open_log_file(&path)?;
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            log_file: Mutex::new(log_file),
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut rotated_path = self.path.clone().into_os_string();
+        rotated_path.push(format!(".{}", index));
+        rotated_path.into()
+    }
+
+    fn rotate(&self, log_file: &mut LogFile) -> io::Result<()> {
+        log_file.file.flush()?;
+
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated_path(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let rotated_path = self.rotated_path(index);
+                if rotated_path.exists() {
+                    std::fs::rename(&rotated_path, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        *log_file = open_log_file(&self.path)?;
+        Ok(())
+    }
+}
+
+impl Writer for RotatingFileWriter {
+    fn write(&self, log: String) {
+        let mut log_file = self.log_file.lock();
+        let line_bytes = log.len() as u64 + 1;
+        let rotation_due = match log_file.rotation_retry_bytes {
+            Some(rotation_retry_bytes) => log_file.size_bytes + line_bytes > rotation_retry_bytes,
+            None => log_file.size_bytes > 0 && log_file.size_bytes + line_bytes > self.max_bytes,
+        };
+        if rotation_due {
+            if let Err(error) = self.rotate(&mut log_file) {
+                if log_file.rotation_retry_bytes.is_none() {
+                    eprintln!(
+                        "Unable to rotate the log file {:?}, retrying after another {} bytes: {}",
+                        self.path, self.max_bytes, error
+                    );
+                }
+                log_file.rotation_retry_bytes = Some(log_file.size_bytes + self.max_bytes);
+            }
+        }
+
+        if let Err(error) = writeln!(log_file.file, "{}", log) {
+            eprintln!("Unable to write to the log file {:?}: {}", self.path, error);
+        } else {
+            log_file.size_bytes += line_bytes;
+        }
+    }
+}
+
+fn open_log_file(path: &Path) -> io::Result<LogFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size_bytes = file.metadata()?.len();
+    Ok(LogFile {
+        file,
+        size_bytes,
+        rotation_retry_bytes: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_temppath::TempPath;
+
+    #[test]
+    fn test_log_file_rotation() {
+        let log_dir = TempPath::new();
+        log_dir.create_as_dir().unwrap();
+        let log_path = log_dir.path().join("node.log");
+
+        // Every line is 11 bytes (including the newline), so each file holds 3 lines
+        let writer = RotatingFileWriter::new(log_path.clone(), 33, 2).unwrap();
+        for index in 0..10 {
+            writer.write(format!("log line {}", index));
+        }
+
+        let read_lines = |path: PathBuf| -> Vec<String> {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|line| line.to_string())
+                .collect()
+        };
+        assert_eq!(read_lines(log_path.clone()), vec!["log line 9"]);
+        assert_eq!(
+            read_lines(log_dir.path().join("node.log.1")),
+            vec!["log line 6", "log line 7", "log line 8"]
+        );
+        assert_eq!(
+            read_lines(log_dir.path().join("node.log.2")),
+            vec!["log line 3", "log line 4", "log line 5"]
+        );
+        assert!(!log_dir.path().join("node.log.3").exists());
+    }
+
+    #[test]
+    fn test_failed_rotation_backoff() {
+        let log_dir = TempPath::new();
+        log_dir.create_as_dir().unwrap();
+        let log_path = log_dir.path().join("node.log");
+        let read_lines = |path: &Path| -> Vec<String> {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|line| line.to_string())
+                .collect()
+        };
+
+        // A directory in place of the rotated file makes the rotation fail
+        let rotated_path = log_dir.path().join("node.log.1");
+        std::fs::create_dir(&rotated_path).unwrap();
+
+        // The rotation fails on the 4th line, and is only retried once another 33 bytes
+        // (i.e., 3 lines) were written, even though it would succeed on the 6th line
+        let writer = RotatingFileWriter::new(log_path.clone(), 33, 1).unwrap();
+        for index in 0..5 {
+            writer.write(format!("log line {}", index));
+        }
+        std::fs::remove_dir(&rotated_path).unwrap();
+        writer.write("log line 5".into());
+        assert_eq!(read_lines(&log_path).len(), 6);
+        assert!(!rotated_path.exists());
+
+        writer.write("log line 6".into());
+        assert_eq!(read_lines(&log_path), vec!["log line 6"]);
+        assert_eq!(read_lines(&rotated_path).len(), 6);
+    }
+}
@@ -2,10 +2,19 @@
 // SPDX-License-Identifier: Apache-2.0
 
 #![forbid(unsafe_code)]
-use aptos_config::config::NodeConfig;
+use aptos_config::{config::NodeConfig, network_id::NetworkId};
+use aptos_node::{
+    db_verification::{verify_node_db, DbVerificationMode},
+    validation::validate_config,
+    NodeOptions,
+};
 use hex::FromHex;
 use rand::{rngs::StdRng, SeedableRng};
-use std::path::PathBuf;
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -45,13 +54,400 @@ struct Args {
         requires("test")
     )]
     lazy: bool,
+
+    #[structopt(
+        long,
+        help = "Maximum number of seconds a validator waits for state sync to initialize before failing startup. Waits indefinitely if not set"
+    )]
+    state_sync_init_timeout_secs: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Network to serve the storage service on (validator, vfn or public). Can be repeated. Defaults to all configured networks",
+        parse(try_from_str = parse_network_id)
+    )]
+    storage_service_network: Vec<NetworkId>,
+
+    #[structopt(
+        long,
+        help = "Always wait for state sync to initialize before starting consensus, even if the DB already satisfies the waypoint"
+    )]
+    always_wait_for_state_sync: bool,
+
+    #[structopt(
+        long,
+        help = "Number of versions behind the highest advertised version at which the node is considered synced (used to report the time to sync)"
+    )]
+    time_to_sync_threshold_versions: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Number of worker threads for a network's runtime, as <network-id>=<threads>. Can be repeated. Defaults to the number of CPU cores",
+        parse(try_from_str = parse_network_worker_threads)
+    )]
+    network_worker_threads: Vec<(NetworkId, usize)>,
+
+    #[structopt(
+        long,
+        help = "Number of worker threads for each state sync runtime (storage service, data client and data streaming service). Defaults to the number of CPU cores"
+    )]
+    state_sync_worker_threads: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "Configured network to skip at startup (vfn or public). Can be repeated",
+        parse(try_from_str = parse_network_id)
+    )]
+    disable_network: Vec<NetworkId>,
+
+    #[structopt(
+        long,
+        help = "Run as a reader node: open the existing DB read-only and only serve the REST API and the legacy storage service"
+    )]
+    reader: bool,
+
+    #[structopt(
+        long,
+        help = "Interval (in seconds) at which the rocksdb properties backing the storage metrics are refreshed. 0 disables the refreshes"
+    )]
+    rocksdb_properties_refresh_interval_secs: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Verify the consistency of the DB (quick or full, quick if no mode is given) before the node starts serving traffic"
+    )]
+    verify_db: Option<Option<DbVerificationMode>>,
+
+    #[structopt(
+        long,
+        help = "Only verify the DB (see --verify-db) and exit, without starting the node",
+        requires("verify-db"),
+        requires("config")
+    )]
+    verify_db_and_exit: bool,
+
+    #[structopt(
+        long,
+        help = "Only validate the node config and exit (with a non-zero status if it's invalid), without starting the node",
+        requires("config")
+    )]
+    validate_only: bool,
+
+    #[structopt(
+        long,
+        help = "Allow the backup service to be bound to a non-loopback address, exposing read access to the whole DB"
+    )]
+    allow_public_backup: bool,
+
+    #[structopt(
+        long,
+        help = "Don't start the legacy storage service (the AptosNet storage service is unaffected)"
+    )]
+    disable_legacy_storage_service: bool,
+
+    #[structopt(
+        long,
+        help = "Capacity of the channel carrying consensus' pull requests to mempool"
+    )]
+    consensus_to_mempool_channel_size: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "Capacity of the channel carrying client (e.g., API) requests to mempool"
+    )]
+    mempool_client_channel_size: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "Capacity of the channels between mempool and each network"
+    )]
+    mempool_network_channel_size: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "Address on which to serve the /live and /ready health probes. Disabled if not set"
+    )]
+    health_check_address: Option<SocketAddr>,
+
+    #[structopt(
+        long,
+        help = "Rotate the log file once it would exceed this many bytes. The log file isn't rotated if not set"
+    )]
+    log_file_max_bytes: Option<u64>,
+
+    #[structopt(long, help = "Number of rotated log files to keep")]
+    log_file_max_files: Option<usize>,
+
+    #[structopt(long, help = "Don't start the REST API")]
+    disable_api: bool,
+
+    #[structopt(
+        long,
+        help = "Don't start the backup service. Backups can't be taken from this node"
+    )]
+    disable_backup_service: bool,
+
+    #[structopt(
+        long,
+        help = "Address on which to serve the debug interface. Overrides the host and port in the config"
+    )]
+    debug_interface_address: Option<SocketAddr>,
+
+    #[structopt(
+        long,
+        help = "Maximum number of seconds to wait for the genesis file to appear if the DB is empty"
+    )]
+    genesis_wait_secs: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Start validators with consensus stopped, until it's explicitly started (e.g., for controlled upgrades)"
+    )]
+    delay_consensus_start: bool,
+
+    #[structopt(
+        long,
+        help = "Interval (in seconds) at which the config is logged. Zero disables the config dumps"
+    )]
+    config_dump_interval_secs: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Interval (in seconds) at which the latest ledger info is logged. Zero disables the ledger dumps"
+    )]
+    ledger_dump_interval_secs: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Never push telemetry. Telemetry is also disabled if APTOS_DISABLE_TELEMETRY is set"
+    )]
+    disable_telemetry: bool,
+
+    #[structopt(
+        long,
+        help = "Average interval (in seconds) at which telemetry is pushed. Zero disables telemetry"
+    )]
+    telemetry_push_interval_secs: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Fail startup if a metric server can't bind its address, instead of running without it"
+    )]
+    require_metrics: bool,
+
+    #[structopt(long, help = "Don't start the (internal) metric server")]
+    disable_metrics_server: bool,
+
+    #[structopt(long, help = "Don't start the public metric server")]
+    disable_public_metrics_server: bool,
+
+    #[structopt(
+        long,
+        help = "Detach from the terminal and run in the background (unix only)"
+    )]
+    daemonize: bool,
+
+    #[structopt(
+        long,
+        help = "Path of the PID file to create. Startup fails if it belongs to a running process"
+    )]
+    pid_file: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "File to redirect stdout to when daemonized",
+        requires("daemonize")
+    )]
+    stdout: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "File to redirect stderr to when daemonized",
+        requires("daemonize")
+    )]
+    stderr: Option<PathBuf>,
+}
+
+impl Args {
+    /// Builds the node options from the flags. Unset flags take their defaults from
+    /// `NodeOptions::default()`, so that each default is only defined once.
+    fn node_options(&self, start_time: Instant) -> NodeOptions {
+        let defaults = NodeOptions::default();
+        NodeOptions {
+            state_sync_initialization_timeout: self
+                .state_sync_init_timeout_secs
+                .map(Duration::from_secs),
+            storage_service_networks: if self.storage_service_network.is_empty() {
+                None
+            } else {
+                Some(self.storage_service_network.clone())
+            },
+            always_wait_for_state_sync: self.always_wait_for_state_sync,
+            time_to_sync_threshold_versions: self
+                .time_to_sync_threshold_versions
+                .unwrap_or(defaults.time_to_sync_threshold_versions),
+            network_runtime_worker_threads: self.network_worker_threads.iter().cloned().collect(),
+            state_sync_runtime_worker_threads: self.state_sync_worker_threads,
+            disabled_networks: self.disable_network.clone(),
+            reader_mode: self.reader,
+            rocksdb_properties_refresh_interval: self
+                .rocksdb_properties_refresh_interval_secs
+                .map_or(
+                    defaults.rocksdb_properties_refresh_interval,
+                    Duration::from_secs,
+                ),
+            db_verification_mode: self
+                .verify_db
+                .map(|mode| mode.unwrap_or(DbVerificationMode::Quick)),
+            allow_public_backup_service: self.allow_public_backup,
+            disable_legacy_storage_service: self.disable_legacy_storage_service,
+            consensus_to_mempool_channel_size: self
+                .consensus_to_mempool_channel_size
+                .unwrap_or(defaults.consensus_to_mempool_channel_size),
+            mempool_client_channel_size: self
+                .mempool_client_channel_size
+                .unwrap_or(defaults.mempool_client_channel_size),
+            mempool_network_channel_size: self
+                .mempool_network_channel_size
+                .unwrap_or(defaults.mempool_network_channel_size),
+            health_check_address: self.health_check_address,
+            log_file_max_bytes: self.log_file_max_bytes,
+            log_file_max_files: self
+                .log_file_max_files
+                .unwrap_or(defaults.log_file_max_files),
+            disable_api: self.disable_api,
+            disable_backup_service: self.disable_backup_service,
+            debug_interface_address: self.debug_interface_address,
+            genesis_wait: self
+                .genesis_wait_secs
+                .map_or(defaults.genesis_wait, Duration::from_secs),
+            delay_consensus_start: self.delay_consensus_start,
+            config_dump_interval: self
+                .config_dump_interval_secs
+                .map_or(defaults.config_dump_interval, Duration::from_secs),
+            ledger_dump_interval: self
+                .ledger_dump_interval_secs
+                .map_or(defaults.ledger_dump_interval, Duration::from_secs),
+            disable_telemetry: self.disable_telemetry,
+            telemetry_push_interval: self
+                .telemetry_push_interval_secs
+                .map_or(defaults.telemetry_push_interval, Duration::from_secs),
+            require_metrics_server: self.require_metrics,
+            disable_metrics_server: self.disable_metrics_server,
+            disable_public_metrics_server: self.disable_public_metrics_server,
+            term: None,
+            start_time: Some(start_time),
+        }
+    }
+}
+
+/// Daemonizes the process and creates the PID file (if requested). The PID file is removed
+/// when the returned handle is dropped.
+#[cfg(unix)]
+fn setup_process(args: &Args) -> Option<aptos_node::daemon::PidFile> {
+    use aptos_node::daemon;
+
+    // Check the PID file before detaching, so that the error is still visible to the operator
+    if let Some(pid_file) = &args.pid_file {
+        if let Some(stale_pid) =
+            daemon::check_pid_file(pid_file).expect("Unable to use the PID file")
+        {
+            eprintln!(
+                "Removed the stale PID file {:?} (of process {}, which is no longer running)",
+                pid_file, stale_pid
+            );
+        }
+    }
+    if args.daemonize {
+        daemon::daemonize(args.stdout.as_deref(), args.stderr.as_deref())
+            .expect("Failed to daemonize the node");
+    }
+    args.pid_file
+        .as_ref()
+        .map(|pid_file| daemon::PidFile::create(pid_file).expect("Failed to create the PID file"))
+}
+
+#[cfg(not(unix))]
+fn setup_process(args: &Args) -> Option<()> {
+    if args.daemonize || args.pid_file.is_some() {
+        eprintln!("--daemonize and --pid-file are only supported on unix platforms");
+        std::process::exit(1);
+    }
+    None
+}
+
+fn parse_network_id(network_id: &str) -> Result<NetworkId, String> {
+    match network_id.to_lowercase().as_str() {
+        "validator" => Ok(NetworkId::Validator),
+        "vfn" => Ok(NetworkId::Vfn),
+        "public" => Ok(NetworkId::Public),
+        _ => Err(format!("Unknown network id: {}", network_id)),
+    }
+}
+
+fn parse_network_worker_threads(value: &str) -> Result<(NetworkId, usize), String> {
+    let (network_id, worker_threads) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <network-id>=<threads>, got: {}", value))?;
+    let worker_threads: usize = worker_threads.parse().map_err(|error| {
+        format!(
+            "Invalid number of worker threads {}: {}",
+            worker_threads, error
+        )
+    })?;
+    if worker_threads == 0 {
+        return Err("The number of worker threads must be at least 1".into());
+    }
+    Ok((parse_network_id(network_id)?, worker_threads))
 }
 
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
 fn main() {
+    // Captured first, so that the startup timings include loading the config, setting up
+    // the logger and daemonizing
+    let start_time = Instant::now();
     let args = Args::from_args();
+    let options = args.node_options(start_time);
+
+    if args.verify_db_and_exit {
+        let config_path = args.config.as_ref().unwrap();
+        let config = NodeConfig::load(config_path).expect("Failed to load node config");
+        match verify_node_db(&config, options.db_verification_mode.unwrap()) {
+            Ok(()) => {
+                println!("DB verification succeeded");
+                std::process::exit(0);
+            }
+            Err(error) => {
+                eprintln!("DB verification failed: {:?}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.validate_only {
+        let config_path = args.config.as_ref().unwrap();
+        let config = NodeConfig::load(config_path).expect("Failed to load node config");
+        match validate_config(&config, &options) {
+            Ok(report) => {
+                for warning in report.warnings() {
+                    println!("Warning: {}", warning);
+                }
+                println!("The node config is valid");
+                std::process::exit(0);
+            }
+            Err(error) => {
+                for problem in error.problems() {
+                    eprintln!("Error: {}", problem);
+                }
+                eprintln!("The node config is invalid");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let _pid_file = setup_process(&args);
 
     if args.test {
         println!("Entering test mode, this should never be used in production!");
@@ -70,11 +466,12 @@ fn main() {
             args.lazy,
             genesis_modules,
             rng,
+            options,
         );
     } else {
         let config = NodeConfig::load(args.config.unwrap()).expect("Failed to load node config");
         println!("Using node config {:?}", &config);
-        aptos_node::start(&config, None);
+        aptos_node::start(&config, options, None);
     };
 }
 #[global_allocator]
@@ -0,0 +1,125 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! The (internal and public) metric servers. Unlike `aptos_metrics::metric_server`, which
+//! blocks the thread it's started on until the process exits, these run on a runtime owned
+//! by the node, so that they stop when the node is torn down.
+
+use anyhow::{Context, Result};
+use aptos_logger::prelude::*;
+use aptos_metrics::{get_all_metrics, get_public_json_metrics};
+use prometheus::{proto::MetricFamily, Encoder, TextEncoder};
+use std::{collections::HashSet, net::SocketAddr};
+use tokio::runtime::Handle;
+use warp::{
+    http::StatusCode,
+    reply::{Reply, Response},
+    Filter,
+};
+
+// The suffixes of the series a histogram or summary is exported as
+const SERIES_SUFFIXES: &[&str] = &["_bucket", "_count", "_sum"];
+
+/// Starts a metric server on the given runtime and returns the address it's bound to. It
+/// serves `/metrics` (in the prometheus text format) and `/json_metrics`. A public metric
+/// server only serves the public metrics.
+pub fn start_metric_server(
+    runtime: &Handle,
+    address: SocketAddr,
+    public_metrics: bool,
+) -> Result<SocketAddr> {
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .map(move || text_metrics(public_metrics));
+    let json_metrics = warp::path("json_metrics")
+        .and(warp::path::end())
+        .map(move || {
+            let metrics = if public_metrics {
+                get_public_json_metrics()
+            } else {
+                get_all_metrics()
+            };
+            warp::reply::json(&metrics).into_response()
+        });
+
+    let _enter = runtime.enter();
+    let (address, server) = warp::serve(metrics.or(json_metrics))
+        .try_bind_ephemeral(address)
+        .with_context(|| format!("Failed to bind the metric server to {}", address))?;
+    runtime.spawn(server);
+    info!(
+        "Metric server (public: {}) listening on {}",
+        public_metrics, address
+    );
+    Ok(address)
+}
+
+fn text_metrics(public_metrics: bool) -> Response {
+    let mut metric_families = prometheus::gather();
+    if public_metrics {
+        let public_metric_names = public_metric_names();
+        metric_families.retain(|metric_family| is_public(metric_family, &public_metric_names));
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    match encoder.encode(&metric_families, &mut buffer) {
+        Ok(()) => {
+            warp::reply::with_header(buffer, "Content-Type", encoder.format_type()).into_response()
+        }
+        Err(error) => warp::reply::with_status(
+            format!("Failed to encode the metrics: {}", error),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response(),
+    }
+}
+
+/// Returns the names of the series in the public metrics (i.e., without their labels)
+fn public_metric_names() -> HashSet<String> {
+    get_public_json_metrics()
+        .into_keys()
+        .map(|key| match key.split_once('{') {
+            Some((name, _)) => name.to_string(),
+            None => key,
+        })
+        .collect()
+}
+
+fn is_public(metric_family: &MetricFamily, public_metric_names: &HashSet<String>) -> bool {
+    let name = metric_family.get_name();
+    public_metric_names.contains(name)
+        || SERIES_SUFFIXES
+            .iter()
+            .any(|suffix| public_metric_names.contains(&format!("{}{}", name, suffix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    #[test]
+    fn test_metric_server_stops_with_runtime() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let address =
+            start_metric_server(runtime.handle(), "127.0.0.1:0".parse().unwrap(), false).unwrap();
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+
+        // Once the runtime is shut down, the address is free again
+        runtime.shutdown_timeout(std::time::Duration::from_secs(5));
+        std::net::TcpListener::bind(address).unwrap();
+    }
+}
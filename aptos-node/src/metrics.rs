@@ -0,0 +1,96 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+use aptos_metrics::{
+    register_gauge_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, GaugeVec, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+};
+use once_cell::sync::Lazy;
+
+/// The number of seconds it took the node to sync to the chain tip after starting up
+pub static TIME_TO_SYNC_SECONDS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_node_time_to_sync_seconds",
+        "Seconds from node startup until it synced to within the threshold of the chain tip"
+    )
+    .unwrap()
+});
+
+/// Counter of peer connections established, by network and direction
+pub static CONNECTIONS_ESTABLISHED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "aptos_node_connections_established",
+        "Number of peer connections established",
+        &["network_id", "direction"]
+    )
+    .unwrap()
+});
+
+/// Counter of peer connections dropped, by network and direction
+pub static CONNECTIONS_DROPPED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "aptos_node_connections_dropped",
+        "Number of peer connections dropped",
+        &["network_id", "direction"]
+    )
+    .unwrap()
+});
+
+/// Histogram of the lifetimes of dropped peer connections
+pub static CONNECTION_LIFETIME_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "aptos_node_connection_lifetime_seconds",
+        "Lifetime of peer connections (in seconds) when they are dropped",
+        &["network_id"]
+    )
+    .unwrap()
+});
+
+/// Gauge of the number of peer connections dropped over the last minute
+pub static CONNECTION_CHURN_RATE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "aptos_node_connection_churn_per_minute",
+        "Number of peer connections dropped over the last minute",
+        &["network_id"]
+    )
+    .unwrap()
+});
+
+/// Counter of metric servers that failed to start, by server
+pub static METRIC_SERVER_START_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "aptos_node_metric_server_start_failures",
+        "Number of metric servers that failed to start (e.g., because their port was in use)",
+        &["server"]
+    )
+    .unwrap()
+});
+
+/// Gauge indicating whether the validator is in the current on-chain validator set
+pub static IN_VALIDATOR_SET: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "aptos_node_in_validator_set",
+        "Whether the validator is in the current on-chain validator set (1) or not (0)"
+    )
+    .unwrap()
+});
+
+/// Gauge (always 1) whose labels describe the node's build, chain and role
+pub static NODE_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "aptos_node_info",
+        "Build, chain and role of the node (the value is always 1)",
+        &["git_hash", "version", "chain_id", "role"]
+    )
+    .unwrap()
+});
+
+/// Gauge of the duration of each startup stage (and of the whole startup, as "total")
+pub static STARTUP_SECONDS: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "aptos_node_startup_seconds",
+        "Seconds taken by each stage of the node startup",
+        &["stage"]
+    )
+    .unwrap()
+});
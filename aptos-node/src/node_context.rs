@@ -0,0 +1,108 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shared state about the running node. It is built once during setup and handed to the
+//! background tasks that need it, instead of each task re-deriving it from the DB or config.
+
+use aptos_config::config::{NodeConfig, RoleType, WaypointConfig};
+use aptos_types::{
+    chain_id::ChainId, ledger_info::LedgerInfoWithSignatures, move_resource::MoveStorage,
+    transaction::Version, waypoint::Waypoint,
+};
+use network::application::storage::PeerMetadataStorage;
+use std::{sync::Arc, time::Instant};
+use storage_interface::DbReader;
+
+pub struct NodeContext {
+    chain_id: ChainId,
+    db: Arc<dyn DbReader>,
+    node_config: NodeConfig,
+    peer_metadata_storage: Arc<PeerMetadataStorage>,
+    role: RoleType,
+    start_time: Instant,
+    waypoint: Option<Waypoint>,
+}
+
+impl NodeContext {
+    pub fn new(
+        node_config: &NodeConfig,
+        chain_id: ChainId,
+        db: Arc<dyn DbReader>,
+        peer_metadata_storage: Arc<PeerMetadataStorage>,
+        start_time: Instant,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            chain_id,
+            db,
+            node_config: node_config.clone(),
+            peer_metadata_storage,
+            role: node_config.base.role,
+            start_time,
+            waypoint: match &node_config.base.waypoint {
+                WaypointConfig::None => None,
+                waypoint_config => Some(waypoint_config.waypoint()),
+            },
+        })
+    }
+
+    pub fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    pub fn db(&self) -> &Arc<dyn DbReader> {
+        &self.db
+    }
+
+    /// Reads the latest ledger info from the DB (if it could be read)
+    pub fn latest_ledger_info(&self) -> Option<LedgerInfoWithSignatures> {
+        self.db.get_latest_ledger_info().ok()
+    }
+
+    pub fn node_config(&self) -> &NodeConfig {
+        &self.node_config
+    }
+
+    pub fn peer_metadata_storage(&self) -> &Arc<PeerMetadataStorage> {
+        &self.peer_metadata_storage
+    }
+
+    pub fn role(&self) -> RoleType {
+        self.role
+    }
+
+    pub fn start_time(&self) -> Instant {
+        self.start_time
+    }
+
+    /// Reads the synced version from the DB (if it could be read)
+    pub fn synced_version(&self) -> Option<Version> {
+        (&*self.db).fetch_synced_version().ok()
+    }
+
+    /// The configured waypoint, if any (e.g., reader nodes may run without one)
+    pub fn waypoint(&self) -> Option<Waypoint> {
+        self.waypoint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_temppath::TempPath;
+    use aptosdb::AptosDB;
+
+    #[test]
+    fn test_no_waypoint() {
+        let node_config = NodeConfig::default();
+        assert!(matches!(node_config.base.waypoint, WaypointConfig::None));
+        let db_dir = TempPath::new();
+        let node_context = NodeContext::new(
+            &node_config,
+            ChainId::test(),
+            Arc::new(AptosDB::new_for_test(&db_dir)),
+            PeerMetadataStorage::new(&[]),
+            Instant::now(),
+        );
+        assert_eq!(node_context.waypoint(), None);
+    }
+}
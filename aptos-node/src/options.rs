@@ -0,0 +1,196 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::db_verification::DbVerificationMode;
+use anyhow::{bail, Result};
+use aptos_config::network_id::NetworkId;
+use aptos_telemetry::constants::NODE_PUSH_TIME_SECS;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_CONFIG_DUMP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const DEFAULT_CONSENSUS_TO_MEMPOOL_CHANNEL_SIZE: usize = 1;
+pub const DEFAULT_LEDGER_DUMP_INTERVAL: Duration = Duration::from_secs(60);
+pub const DEFAULT_LOG_FILE_MAX_FILES: usize = 5;
+pub const DEFAULT_MEMPOOL_CLIENT_CHANNEL_SIZE: usize = 1_024;
+pub const DEFAULT_MEMPOOL_NETWORK_CHANNEL_SIZE: usize = 1_024;
+pub const DEFAULT_ROCKSDB_PROPERTIES_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+pub const DEFAULT_TELEMETRY_PUSH_INTERVAL: Duration = Duration::from_secs(NODE_PUSH_TIME_SECS);
+pub const DEFAULT_TIME_TO_SYNC_THRESHOLD_VERSIONS: u64 = 1_000;
+
+/// Startup options supplied by the operator when launching the node (e.g., via the
+/// command line), as opposed to those persisted in the `NodeConfig`.
+#[derive(Clone, Debug)]
+pub struct NodeOptions {
+    /// The maximum time to wait for state sync to initialize before starting consensus.
+    /// If `None`, the node waits indefinitely.
+    pub state_sync_initialization_timeout: Option<Duration>,
+    /// The networks on which to serve the storage service. If `None`, the storage
+    /// service is served on every configured network.
+    pub storage_service_networks: Option<Vec<NetworkId>>,
+    /// Whether validators should always wait for state sync to initialize before starting
+    /// consensus, even if the DB already satisfies the waypoint.
+    pub always_wait_for_state_sync: bool,
+    /// The number of versions behind the highest advertised version at which the node is
+    /// considered synced (when recording the time to sync after startup).
+    pub time_to_sync_threshold_versions: u64,
+    /// The number of worker threads for each network's runtime. Networks without an
+    /// entry use tokio's default (one worker per CPU core).
+    pub network_runtime_worker_threads: HashMap<NetworkId, usize>,
+    /// The number of worker threads for each state sync runtime (i.e., the storage service,
+    /// data client and data streaming service runtimes). If `None`, tokio's default is used.
+    pub state_sync_runtime_worker_threads: Option<usize>,
+    /// The configured networks that should not be started
+    pub disabled_networks: Vec<NetworkId>,
+    /// Whether to run as a reader node, which serves the API and the legacy storage service
+    /// off a DB opened read-only, without starting any networks, state sync, mempool or
+    /// consensus.
+    pub reader_mode: bool,
+    /// The interval at which the rocksdb properties (backing the storage metrics) are
+    /// refreshed. A zero interval disables the refreshes.
+    pub rocksdb_properties_refresh_interval: Duration,
+    /// If set, the DB is verified for consistency before the node starts serving traffic
+    pub db_verification_mode: Option<DbVerificationMode>,
+    /// Whether the backup service may be bound to a non-loopback address
+    pub allow_public_backup_service: bool,
+    /// Whether to skip starting the legacy (direct) storage service
+    pub disable_legacy_storage_service: bool,
+    /// The capacity of the channel carrying consensus' pull requests to mempool. Consensus
+    /// blocks on proposal creation while the channel is full.
+    pub consensus_to_mempool_channel_size: usize,
+    /// The capacity of the channel carrying client (e.g., API) requests to mempool
+    pub mempool_client_channel_size: usize,
+    /// The capacity of the channels between mempool and each network
+    pub mempool_network_channel_size: usize,
+    /// Whether the node should fail to start if a metric server can't bind its address
+    pub require_metrics_server: bool,
+    /// Whether to skip starting the (internal) metric server
+    pub disable_metrics_server: bool,
+    /// Whether to skip starting the public metric server
+    pub disable_public_metrics_server: bool,
+    /// If set, the address on which to serve the liveness and readiness probes
+    pub health_check_address: Option<SocketAddr>,
+    /// If set, the log file (if any) is rotated once it would exceed this size
+    pub log_file_max_bytes: Option<u64>,
+    /// The number of rotated log files to keep
+    pub log_file_max_files: usize,
+    /// Whether to skip starting the REST API (so that nothing binds the API address)
+    pub disable_api: bool,
+    /// Whether to skip starting the backup service (so that nothing binds its address)
+    pub disable_backup_service: bool,
+    /// If set, the address on which to serve the debug interface, instead of resolving the
+    /// configured debug interface host
+    pub debug_interface_address: Option<SocketAddr>,
+    /// The maximum time to wait for the genesis file to appear (e.g., when it's delivered by
+    /// a sidecar) if the DB is empty and no genesis transaction was loaded with the config
+    pub genesis_wait: Duration,
+    /// Whether validators should hold off on starting consensus until
+    /// `AptosHandle::start_consensus` is called
+    pub delay_consensus_start: bool,
+    /// The interval at which the config and command line arguments are logged. A zero
+    /// interval disables the dumps.
+    pub config_dump_interval: Duration,
+    /// The interval at which the latest ledger info is logged. A zero interval disables the
+    /// dumps.
+    pub ledger_dump_interval: Duration,
+    /// Whether to disable telemetry, i.e., never push any data to the telemetry service
+    pub disable_telemetry: bool,
+    /// The (average) interval at which telemetry is pushed. A zero interval disables
+    /// telemetry.
+    pub telemetry_push_interval: Duration,
+    /// If set, startup waits (e.g., for state sync to initialize) are aborted as soon as
+    /// this flag is set, e.g., by a termination signal
+    pub term: Option<Arc<AtomicBool>>,
+    /// The time the node process started, from which the startup timings are measured. If
+    /// `None`, they're measured from the start of `setup_environment`.
+    pub start_time: Option<Instant>,
+}
+
+impl Default for NodeOptions {
+    fn default() -> Self {
+        Self {
+            state_sync_initialization_timeout: None,
+            storage_service_networks: None,
+            always_wait_for_state_sync: false,
+            time_to_sync_threshold_versions: DEFAULT_TIME_TO_SYNC_THRESHOLD_VERSIONS,
+            network_runtime_worker_threads: HashMap::new(),
+            state_sync_runtime_worker_threads: None,
+            disabled_networks: vec![],
+            reader_mode: false,
+            rocksdb_properties_refresh_interval: DEFAULT_ROCKSDB_PROPERTIES_REFRESH_INTERVAL,
+            db_verification_mode: None,
+            allow_public_backup_service: false,
+            disable_legacy_storage_service: false,
+            consensus_to_mempool_channel_size: DEFAULT_CONSENSUS_TO_MEMPOOL_CHANNEL_SIZE,
+            mempool_client_channel_size: DEFAULT_MEMPOOL_CLIENT_CHANNEL_SIZE,
+            mempool_network_channel_size: DEFAULT_MEMPOOL_NETWORK_CHANNEL_SIZE,
+            health_check_address: None,
+            log_file_max_bytes: None,
+            log_file_max_files: DEFAULT_LOG_FILE_MAX_FILES,
+            disable_api: false,
+            disable_backup_service: false,
+            debug_interface_address: None,
+            genesis_wait: Duration::ZERO,
+            delay_consensus_start: false,
+            config_dump_interval: DEFAULT_CONFIG_DUMP_INTERVAL,
+            ledger_dump_interval: DEFAULT_LEDGER_DUMP_INTERVAL,
+            disable_telemetry: false,
+            telemetry_push_interval: DEFAULT_TELEMETRY_PUSH_INTERVAL,
+            term: None,
+            start_time: None,
+            require_metrics_server: false,
+            disable_metrics_server: false,
+            disable_public_metrics_server: false,
+        }
+    }
+}
+
+impl NodeOptions {
+    /// Verifies that all internal channels have a non-zero capacity
+    pub fn validate_channel_sizes(&self) -> Result<()> {
+        let channel_sizes = [
+            (
+                "consensus to mempool",
+                self.consensus_to_mempool_channel_size,
+            ),
+            ("mempool client", self.mempool_client_channel_size),
+            ("mempool network", self.mempool_network_channel_size),
+        ];
+        let empty_channels: Vec<&str> = channel_sizes
+            .iter()
+            .filter(|(_, size)| *size == 0)
+            .map(|(name, _)| *name)
+            .collect();
+        if !empty_channels.is_empty() {
+            bail!(
+                "Channel sizes must be non-zero! Invalid channels: {}",
+                empty_channels.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_channel_sizes() {
+        NodeOptions::default().validate_channel_sizes().unwrap();
+
+        let options = NodeOptions {
+            mempool_client_channel_size: 0,
+            mempool_network_channel_size: 0,
+            ..NodeOptions::default()
+        };
+        let error = options.validate_channel_sizes().unwrap_err().to_string();
+        assert!(error.contains("mempool client"));
+        assert!(error.contains("mempool network"));
+        assert!(!error.contains("consensus to mempool"));
+    }
+}
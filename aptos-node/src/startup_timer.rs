@@ -0,0 +1,141 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Timings of the node's startup stages, exported as metrics so that startup regressions can
+//! be tracked across releases.
+
+use crate::metrics::STARTUP_SECONDS;
+use aptos_logger::prelude::*;
+use std::time::{Duration, Instant};
+
+/// The stage label under which the total startup time is recorded
+const TOTAL_STAGE: &str = "total";
+/// The stage label under which the time until state sync initialized is recorded
+const STATE_SYNC_INITIALIZED_STAGE: &str = "state_sync_initialized";
+
+/// Records the duration of each startup stage, in the order the stages complete
+pub struct StartupTimer {
+    start_time: Instant,
+    stage_start_time: Instant,
+    stages: Vec<(&'static str, Duration)>,
+    state_sync_initialized: Option<Duration>,
+}
+
+impl StartupTimer {
+    /// Creates a timer for a node that started at `start_time`. The first stage starts now.
+    pub fn new(start_time: Instant) -> Self {
+        Self {
+            start_time,
+            stage_start_time: Instant::now(),
+            stages: vec![],
+            state_sync_initialized: None,
+        }
+    }
+
+    /// Starts timing the next stage, excluding any time spent since the previous stage
+    pub fn start_stage(&mut self) {
+        self.stage_start_time = Instant::now();
+    }
+
+    /// Records the duration of the given stage (since the previous stage finished, or since
+    /// the last call to `start_stage`) and starts timing the next one.
+    pub fn finish_stage(&mut self, stage: &'static str) -> Duration {
+        let duration = self.stage_start_time.elapsed();
+        STARTUP_SECONDS
+            .with_label_values(&[stage])
+            .set(duration.as_secs_f64());
+        debug!("{} started in {} ms", stage, duration.as_millis());
+
+        self.stages.push((stage, duration));
+        self.stage_start_time = Instant::now();
+        duration
+    }
+
+    /// Records the time from node startup until state sync initialized (or until the wait
+    /// for it was skipped)
+    pub fn set_state_sync_initialized(&mut self) {
+        self.state_sync_initialized = Some(record_state_sync_initialized(self.start_time));
+    }
+
+    #[cfg(test)]
+    pub fn stages(&self) -> &[(&'static str, Duration)] {
+        &self.stages
+    }
+
+    /// Records the total startup time and logs a summary of all stages. This should be
+    /// called once the node is fully up.
+    pub fn log_summary(&self) {
+        let total = self.start_time.elapsed();
+        STARTUP_SECONDS
+            .with_label_values(&[TOTAL_STAGE])
+            .set(total.as_secs_f64());
+
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|(stage, duration)| format!("{}={}ms", stage, duration.as_millis()))
+            .collect();
+        info!(
+            total_ms = total.as_millis() as u64,
+            state_sync_initialized_ms = self
+                .state_sync_initialized
+                .map(|duration| duration.as_millis() as u64),
+            stages = stages.join(", "),
+            "Node started"
+        );
+    }
+}
+
+/// Records the time from node startup (at `start_time`) until state sync initialized. This
+/// is for state sync initializations that complete after the startup summary was logged
+/// (e.g., in the background on full nodes).
+pub fn record_state_sync_initialized(start_time: Instant) -> Duration {
+    let duration = start_time.elapsed();
+    STARTUP_SECONDS
+        .with_label_values(&[STATE_SYNC_INITIALIZED_STAGE])
+        .set(duration.as_secs_f64());
+    duration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_stages() {
+        let mut timer = StartupTimer::new(Instant::now());
+        timer.finish_stage("test_stage_a");
+        std::thread::sleep(Duration::from_millis(10));
+        timer.start_stage();
+        let duration = timer.finish_stage("test_stage_b");
+        assert!(duration < Duration::from_millis(10));
+
+        let stages: Vec<_> = timer.stages().iter().map(|(stage, _)| *stage).collect();
+        assert_eq!(stages, vec!["test_stage_a", "test_stage_b"]);
+        assert_eq!(
+            STARTUP_SECONDS.with_label_values(&["test_stage_b"]).get(),
+            duration.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_state_sync_initialized_without_wait() {
+        // On the fast path, state sync initialization is recorded without a wait stage, and
+        // is measured from the process start (before the timer was created)
+        let start_time = Instant::now();
+        std::thread::sleep(Duration::from_millis(10));
+        let mut timer = StartupTimer::new(start_time);
+        timer.finish_stage("test_stage_c");
+        timer.set_state_sync_initialized();
+
+        let state_sync_initialized = timer.state_sync_initialized.unwrap();
+        assert!(state_sync_initialized >= Duration::from_millis(10));
+        assert!(state_sync_initialized >= timer.stages()[0].1);
+        assert!(
+            STARTUP_SECONDS
+                .with_label_values(&[STATE_SYNC_INITIALIZED_STAGE])
+                .get()
+                >= 0.01
+        );
+    }
+}
@@ -0,0 +1,14 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Keys of the node-specific fields pushed to the telemetry service, in addition to those
+//! defined in `aptos_telemetry::constants`.
+
+/// The number of connected peers on a network. The key is followed by the network id.
+pub const CONNECTED_PEERS_METRIC_PREFIX: &str = "connected_peers_";
+/// The number of transactions in mempool
+pub const MEMPOOL_TXN_COUNT_METRIC: &str = "mempool_txn_count";
+/// The number of seconds it took the node to sync to the chain tip after starting up
+pub const TIME_TO_SYNC_METRIC: &str = "time_to_sync_secs";
+/// The number of versions the node trails the highest version advertised by its peers
+pub const VERSION_LAG_METRIC: &str = "version_lag";
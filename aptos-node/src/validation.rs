@@ -0,0 +1,881 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks performed on a NodeConfig before any of the node's components are started.
+
+use crate::options::NodeOptions;
+use anyhow::{anyhow, bail, Context, Result};
+use aptos_config::{
+    config::{NodeConfig, WaypointConfig},
+    network_id::NetworkId,
+    utils::get_genesis_txn,
+};
+use aptos_global_constants::{GENESIS_WAYPOINT, WAYPOINT};
+use aptos_logger::prelude::*;
+use aptos_secure_storage::{KVStorage, Storage};
+use aptos_temppath::TempPath;
+use aptos_types::{transaction::Transaction, waypoint::Waypoint};
+use aptos_vm::AptosVM;
+use aptosdb::AptosDB;
+use executor::db_bootstrapper::generate_waypoint;
+use std::{
+    fmt,
+    fs::OpenOptions,
+    net::{IpAddr, SocketAddr, TcpListener, ToSocketAddrs},
+    path::Path,
+};
+use storage_interface::DbReaderWriter;
+
+/// The problems found while validating a NodeConfig
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigError {
+    problems: Vec<String>,
+}
+
+impl ConfigError {
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid node config: {}", self.problems.join("; "))
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The outcome of a config validation that found no problems. Warnings point out settings
+/// that are valid, but likely unintended.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+/// Performs every static check of the config (as the node would run it with the given
+/// options), without opening the node's DB or binding any address, and reports all problems
+/// found at once.
+pub fn validate_config(
+    node_config: &NodeConfig,
+    options: &NodeOptions,
+) -> Result<ValidationReport, ConfigError> {
+    let mut problems = vec![];
+    let mut report = ValidationReport::default();
+
+    check_genesis(node_config, &mut problems, &mut report.warnings);
+    check_waypoint(node_config, &mut problems);
+    if let Err(error) = validate_network_configs(node_config) {
+        problems.extend(error.problems);
+    }
+    check_addresses(node_config, options, &mut problems);
+    check_storage_dir_writable(node_config, &mut problems);
+
+    if problems.is_empty() {
+        Ok(report)
+    } else {
+        Err(ConfigError { problems })
+    }
+}
+
+/// Verifies that the configured genesis file (if any) was loaded
+fn check_genesis(node_config: &NodeConfig, problems: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let genesis_file_location = &node_config.execution.genesis_file_location;
+    if get_genesis_txn(node_config).is_some() {
+        return;
+    }
+    if genesis_file_location.as_os_str().is_empty() {
+        warnings.push(
+            "No genesis transaction is configured. The node can only start from a DB that \
+            already contains genesis"
+                .into(),
+        );
+    } else {
+        problems.push(format!(
+            "The genesis transaction couldn't be loaded from {:?}",
+            genesis_file_location
+        ));
+    }
+}
+
+/// Verifies that the waypoint can be read and, if a genesis transaction is configured as
+/// well, that the genesis waypoint matches it
+fn check_waypoint(node_config: &NodeConfig, problems: &mut Vec<String>) {
+    let genesis_waypoint = match read_genesis_waypoint(node_config) {
+        Ok(genesis_waypoint) => genesis_waypoint,
+        Err(error) => {
+            problems.push(format!("{:#}", error));
+            return;
+        }
+    };
+
+    if let Some(genesis) = get_genesis_txn(node_config) {
+        match generate_genesis_waypoint(node_config, genesis) {
+            Ok(waypoint) if waypoint != genesis_waypoint => problems.push(format!(
+                "The genesis waypoint {} doesn't match the genesis transaction, whose waypoint \
+                is {}. The node couldn't bootstrap an empty DB",
+                genesis_waypoint, waypoint
+            )),
+            Ok(_) => {}
+            Err(error) => problems.push(format!(
+                "The waypoint of the genesis transaction couldn't be computed: {:#}",
+                error
+            )),
+        }
+    }
+}
+
+/// Reads the configured genesis waypoint. Unlike `WaypointConfig::genesis_waypoint`, this
+/// returns an error instead of panicking when the waypoint is missing or can't be read.
+fn read_genesis_waypoint(node_config: &NodeConfig) -> Result<Waypoint> {
+    match &node_config.base.waypoint {
+        WaypointConfig::FromStorage(backend) => {
+            let storage: Storage = backend.into();
+            // The node reads the latest waypoint as well, so it must be readable too
+            storage.get::<Waypoint>(WAYPOINT).map_err(|error| {
+                anyhow!(
+                    "The waypoint couldn't be read from secure storage: {}",
+                    error
+                )
+            })?;
+            storage
+                .get::<Waypoint>(GENESIS_WAYPOINT)
+                .map(|response| response.value)
+                .map_err(|error| {
+                    anyhow!(
+                        "The genesis waypoint couldn't be read from secure storage: {}",
+                        error
+                    )
+                })
+        }
+        waypoint_config => waypoint_config
+            .waypoint_from_config()
+            .ok_or_else(|| anyhow!("No waypoint is configured (or the waypoint couldn't be read)")),
+    }
+}
+
+/// Computes the waypoint of the given genesis transaction by applying it to a temporary DB
+fn generate_genesis_waypoint(node_config: &NodeConfig, genesis: &Transaction) -> Result<Waypoint> {
+    let db_dir = TempPath::new();
+    db_dir.create_as_dir()?;
+    let db = AptosDB::open(
+        db_dir.path(),
+        false, /* readonly */
+        node_config.storage.storage_pruner_config,
+        node_config.storage.rocksdb_config,
+    )?;
+    generate_waypoint::<AptosVM>(&DbReaderWriter::new(db), genesis)
+}
+
+/// Verifies that every listen address resolves and that none of them conflict
+fn check_addresses(node_config: &NodeConfig, options: &NodeOptions, problems: &mut Vec<String>) {
+    let network_configs = node_config
+        .validator_network
+        .iter()
+        .chain(node_config.full_node_networks.iter());
+    for network_config in network_configs {
+        if let Err(error) = network_config.listen_address.to_socket_addrs() {
+            problems.push(format!(
+                "The {} network listen address {} doesn't resolve: {}",
+                network_config.network_id, network_config.listen_address, error
+            ));
+        }
+    }
+
+    // The configured debug interface host is only used without an address override
+    let debug_interface = &node_config.debug_interface;
+    if let Err(error) = resolve_host(
+        &debug_interface.address,
+        debug_interface.admission_control_node_debug_port,
+    ) {
+        if options.debug_interface_address.is_none() {
+            problems.push(format!(
+                "The debug interface address doesn't resolve: {:#}",
+                error
+            ));
+        }
+    }
+
+    if let Err(error) = check_listen_address_conflicts(node_config, options) {
+        problems.push(error.to_string());
+    }
+}
+
+/// Verifies that this process can write to the storage directory (or, if it doesn't exist
+/// yet, to the closest existing ancestor it would be created in), by creating and removing
+/// a probe file in it
+fn check_storage_dir_writable(node_config: &NodeConfig, problems: &mut Vec<String>) {
+    let storage_dir = node_config.storage.dir();
+    let existing_dir = match storage_dir.ancestors().find(|path| path.exists()) {
+        Some(existing_dir) => existing_dir,
+        None => {
+            problems.push(format!(
+                "The storage directory {:?} isn't writable: no ancestor exists",
+                storage_dir
+            ));
+            return;
+        }
+    };
+    if let Err(error) = probe_dir_writable(existing_dir) {
+        problems.push(format!(
+            "The storage directory {:?} isn't writable: {}",
+            storage_dir, error
+        ));
+    }
+}
+
+fn probe_dir_writable(dir: &Path) -> std::io::Result<()> {
+    let probe_path = dir.join(format!(".aptos-write-probe-{}", std::process::id()));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe_path)?;
+    std::fs::remove_file(&probe_path)
+}
+
+/// Verifies that every network in the config has a unique id, and that there is exactly one
+/// validator network on validators (and none otherwise). Returns the ids of all configured
+/// networks.
+pub fn validate_network_configs(node_config: &NodeConfig) -> Result<Vec<NetworkId>, ConfigError> {
+    // The networks, labeled by where they're configured
+    let networks: Vec<(String, NetworkId)> = node_config
+        .full_node_networks
+        .iter()
+        .enumerate()
+        .map(|(index, network_config)| {
+            (
+                format!("full_node_networks[{}]", index),
+                network_config.network_id,
+            )
+        })
+        .chain(
+            node_config
+                .validator_network
+                .iter()
+                .map(|network_config| ("validator_network".into(), network_config.network_id)),
+        )
+        .collect();
+    let network_ids: Vec<NetworkId> = networks.iter().map(|(_, network_id)| *network_id).collect();
+
+    let mut unique_network_ids = vec![];
+    let mut duplicate_network_ids = vec![];
+    for network_id in &network_ids {
+        if !unique_network_ids.contains(network_id) {
+            unique_network_ids.push(*network_id);
+        } else if !duplicate_network_ids.contains(network_id) {
+            duplicate_network_ids.push(*network_id);
+        }
+    }
+    let mut problems: Vec<String> = duplicate_network_ids
+        .iter()
+        .map(|network_id| {
+            format!(
+                "Duplicate NetworkId: '{}'. Can't start node with duplicate networks",
+                network_id
+            )
+        })
+        .collect();
+
+    let validator_networks: Vec<&str> = networks
+        .iter()
+        .filter(|(_, network_id)| network_id.is_validator_network())
+        .map(|(location, _)| location.as_str())
+        .collect();
+    if validator_networks.len() > 1 {
+        problems.push(format!(
+            "There can be at most one validator network, found {}: {}",
+            validator_networks.len(),
+            validator_networks.join(", ")
+        ));
+    } else if validator_networks.is_empty() && node_config.base.role.is_validator() {
+        problems.push(
+            "The node is a validator, but no validator network is configured. Consensus could \
+            never start"
+                .into(),
+        );
+    } else if !validator_networks.is_empty() && !node_config.base.role.is_validator() {
+        problems.push(format!(
+            "The node is a {}, but a validator network is configured ({}). Only validators \
+            can run consensus",
+            node_config.base.role,
+            validator_networks.join(", ")
+        ));
+    }
+
+    if problems.is_empty() {
+        Ok(unique_network_ids)
+    } else {
+        Err(ConfigError { problems })
+    }
+}
+
+/// Verifies that no two listen addresses the node would bind (the network listen addresses
+/// and the API, debug interface, metrics server and health check addresses) conflict with
+/// each other. The addresses are those in effect with the given options, i.e., overridden
+/// addresses replace the configured ones and disabled services are skipped.
+pub fn check_listen_address_conflicts(
+    node_config: &NodeConfig,
+    options: &NodeOptions,
+) -> Result<()> {
+    let listen_addresses = get_listen_addresses(node_config, options);
+
+    let mut conflicts = vec![];
+    for (index, (name, address)) in listen_addresses.iter().enumerate() {
+        for (other_name, other_address) in &listen_addresses[index + 1..] {
+            if addresses_conflict(address, other_address) {
+                conflicts.push(format!(
+                    "{} ({}) conflicts with {} ({})",
+                    name, address, other_name, other_address
+                ));
+            }
+        }
+    }
+
+    if !conflicts.is_empty() {
+        bail!("Conflicting listen addresses: {}", conflicts.join("; "));
+    }
+    Ok(())
+}
+
+/// Verifies that the backup service (which provides unauthenticated read access to the
+/// whole DB) is only bound to a loopback address, unless public access is explicitly allowed.
+pub fn check_backup_service_address(
+    backup_service_address: SocketAddr,
+    allow_public_backup_service: bool,
+) -> Result<()> {
+    if backup_service_address.ip().is_loopback() {
+        return Ok(());
+    }
+
+    if allow_public_backup_service {
+        warn!(
+            "The backup service is bound to the non-loopback address {}. This exposes full \
+            (unauthenticated) read access to the DB!",
+            backup_service_address
+        );
+        Ok(())
+    } else {
+        bail!(
+            "The backup service address {} is not a loopback address, which would expose full \
+            (unauthenticated) read access to the DB. Bind it to a loopback address or pass \
+            --allow-public-backup to override.",
+            backup_service_address
+        )
+    }
+}
+
+/// Verifies that the given address can be bound, by binding (and immediately releasing) it.
+/// This catches, e.g., ports already in use by another process before a server is started.
+pub fn check_address_bindable(host: &str, port: u16) -> Result<()> {
+    resolve_bindable_address(host, port).map(|_| ())
+}
+
+/// Resolves the given host and port to all candidate addresses. The host may be an IPv4
+/// literal, an IPv6 literal (with or without brackets), or a hostname.
+pub fn resolve_host(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    let host = host.trim();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    let addresses: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("Unable to resolve {}", host))?
+        .collect();
+    if addresses.is_empty() {
+        bail!("{} doesn't resolve to any address", host);
+    }
+    Ok(addresses)
+}
+
+/// Returns the first of the host's candidate addresses (see `resolve_host`) that can be
+/// bound. Binding is checked by binding (and immediately releasing) each candidate.
+pub fn resolve_bindable_address(host: &str, port: u16) -> Result<SocketAddr> {
+    let addresses = resolve_host(host, port)?;
+    let mut bind_errors = vec![];
+    for address in addresses {
+        match TcpListener::bind(address) {
+            Ok(_) => return Ok(address),
+            Err(error) => bind_errors.push(format!("{} ({})", address, error)),
+        }
+    }
+    bail!(
+        "Unable to bind to {}:{}. Tried: {}",
+        host,
+        port,
+        bind_errors.join(", ")
+    )
+}
+
+/// Returns all (resolved) addresses the node will listen on, labeled by their owner.
+/// Addresses that fail to resolve are skipped, as they'll fail elsewhere with a better error.
+fn get_listen_addresses(
+    node_config: &NodeConfig,
+    options: &NodeOptions,
+) -> Vec<(String, SocketAddr)> {
+    let mut listen_addresses = vec![];
+
+    let network_configs = node_config
+        .validator_network
+        .iter()
+        .chain(node_config.full_node_networks.iter());
+    for network_config in network_configs {
+        if let Ok(addresses) = network_config.listen_address.to_socket_addrs() {
+            let name = format!("{} network listen address", network_config.network_id);
+            for address in addresses {
+                listen_addresses.push((name.clone(), address));
+            }
+        }
+    }
+
+    if !options.disable_api {
+        listen_addresses.push(("API address".into(), node_config.api.address));
+    }
+    if let Some(health_check_address) = options.health_check_address {
+        listen_addresses.push(("health check address".into(), health_check_address));
+    }
+
+    let debug_interface = &node_config.debug_interface;
+    match options.debug_interface_address {
+        Some(debug_interface_address) => {
+            listen_addresses.push(("debug interface address".into(), debug_interface_address))
+        }
+        None => {
+            if let Ok(addresses) = resolve_host(
+                &debug_interface.address,
+                debug_interface.admission_control_node_debug_port,
+            ) {
+                for address in addresses {
+                    listen_addresses.push(("debug interface address".into(), address));
+                }
+            }
+        }
+    }
+
+    let metrics_server_ports = [
+        (
+            "metrics server",
+            debug_interface.metrics_server_port,
+            options.disable_metrics_server,
+        ),
+        (
+            "public metrics server",
+            debug_interface.public_metrics_server_port,
+            options.disable_public_metrics_server,
+        ),
+    ];
+    for (name, port, disabled) in metrics_server_ports {
+        if disabled {
+            continue;
+        }
+        if let Ok(addresses) = resolve_host(&debug_interface.address, port) {
+            for address in addresses {
+                listen_addresses.push((format!("{} address", name), address));
+            }
+        }
+    }
+
+    listen_addresses
+}
+
+/// Returns true iff binding both addresses would conflict. Wildcard ports (0) never
+/// conflict, and an unspecified IP conflicts with any IP of the same family (an unspecified
+/// IPv6 address is treated as dual-stack, so it conflicts with IPv4 addresses as well).
+fn addresses_conflict(address: &SocketAddr, other_address: &SocketAddr) -> bool {
+    if address.port() == 0 || address.port() != other_address.port() {
+        return false;
+    }
+
+    let (ip, other_ip) = (address.ip(), other_address.ip());
+    if ip == other_ip {
+        return true;
+    }
+    let is_dual_stack_wildcard =
+        |address: &SocketAddr| address.is_ipv6() && address.ip().is_unspecified();
+    if is_dual_stack_wildcard(address) || is_dual_stack_wildcard(other_address) {
+        return true;
+    }
+    address.is_ipv4() == other_address.is_ipv4()
+        && (ip.is_unspecified() || other_ip.is_unspecified())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_config::config::{NetworkConfig, RoleType};
+    use aptos_genesis_tool::validator_builder::ValidatorBuilder;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::path::PathBuf;
+
+    fn node_config_with_networks(
+        validator_network: Option<NetworkId>,
+        full_node_networks: &[NetworkId],
+    ) -> NodeConfig {
+        let mut node_config = NodeConfig::default();
+        node_config.base.role = if validator_network.is_some() {
+            RoleType::Validator
+        } else {
+            RoleType::FullNode
+        };
+        node_config.validator_network = validator_network.map(NetworkConfig::network_with_id);
+        node_config.full_node_networks = full_node_networks
+            .iter()
+            .map(|network_id| NetworkConfig::network_with_id(*network_id))
+            .collect();
+        node_config
+    }
+
+    #[test]
+    fn test_validate_network_configs() {
+        let node_config = node_config_with_networks(Some(NetworkId::Validator), &[NetworkId::Vfn]);
+        assert_eq!(
+            validate_network_configs(&node_config).unwrap(),
+            vec![NetworkId::Vfn, NetworkId::Validator]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_network_ids() {
+        let node_config = node_config_with_networks(
+            None,
+            &[
+                NetworkId::Public,
+                NetworkId::Vfn,
+                NetworkId::Public,
+                NetworkId::Public,
+            ],
+        );
+        let error = validate_network_configs(&node_config).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("Duplicate NetworkId"));
+    }
+
+    #[test]
+    fn test_multiple_validator_networks() {
+        let node_config =
+            node_config_with_networks(Some(NetworkId::Validator), &[NetworkId::Validator]);
+        let error = validate_network_configs(&node_config).unwrap_err();
+        assert!(error.problems().iter().any(|problem| problem.contains(
+            "at most one validator network, found 2: full_node_networks[0], validator_network"
+        )));
+    }
+
+    #[test]
+    fn test_validator_without_validator_network() {
+        // Fullnodes don't need a validator network
+        let mut node_config = node_config_with_networks(None, &[NetworkId::Public]);
+        validate_network_configs(&node_config).unwrap();
+
+        node_config.base.role = RoleType::Validator;
+        let error = validate_network_configs(&node_config).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("no validator network is configured"));
+    }
+
+    #[test]
+    fn test_full_node_with_validator_network() {
+        let mut node_config =
+            node_config_with_networks(Some(NetworkId::Validator), &[NetworkId::Public]);
+        node_config.base.role = RoleType::FullNode;
+        let error = validate_network_configs(&node_config).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("validator network is configured"));
+    }
+
+    #[test]
+    fn test_check_address_bindable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(check_address_bindable("127.0.0.1", port).is_err());
+
+        drop(listener);
+        check_address_bindable("127.0.0.1", 0).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_host() {
+        let resolve = |host: &str| resolve_host(host, 6180).unwrap();
+        let ipv4: SocketAddr = "127.0.0.1:6180".parse().unwrap();
+        let ipv6: SocketAddr = "[::1]:6180".parse().unwrap();
+
+        assert_eq!(resolve("127.0.0.1"), vec![ipv4]);
+        assert_eq!(resolve("::1"), vec![ipv6]);
+        assert_eq!(resolve("[::1]"), vec![ipv6]);
+        assert!(resolve("localhost")
+            .iter()
+            .all(|address| address.ip().is_loopback() && address.port() == 6180));
+        assert!(resolve_host("[::1", 6180).is_err());
+    }
+
+    #[test]
+    fn test_resolve_bindable_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let error = resolve_bindable_address("127.0.0.1", address.port())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(&format!("Tried: {}", address)));
+
+        drop(listener);
+        assert_eq!(
+            resolve_bindable_address("127.0.0.1", address.port()).unwrap(),
+            address
+        );
+    }
+
+    fn conflict(address: &str, other_address: &str) -> bool {
+        addresses_conflict(&address.parse().unwrap(), &other_address.parse().unwrap())
+    }
+
+    #[test]
+    fn test_addresses_conflict() {
+        // Identical addresses conflict
+        assert!(conflict("127.0.0.1:6180", "127.0.0.1:6180"));
+        assert!(conflict("[::1]:6180", "[::1]:6180"));
+
+        // Different ports or different specific IPs don't conflict
+        assert!(!conflict("127.0.0.1:6180", "127.0.0.1:6181"));
+        assert!(!conflict("127.0.0.1:6180", "10.0.0.1:6180"));
+
+        // Host wildcards conflict with specific IPs of the same family
+        assert!(conflict("0.0.0.0:6180", "127.0.0.1:6180"));
+        assert!(conflict("127.0.0.1:6180", "0.0.0.0:6180"));
+        assert!(!conflict("0.0.0.0:6180", "[::1]:6180"));
+
+        // IPv6 host wildcards are dual-stack
+        assert!(conflict("[::]:6180", "[::1]:6180"));
+        assert!(conflict("[::]:6180", "127.0.0.1:6180"));
+
+        // Wildcard ports are exempt
+        assert!(!conflict("0.0.0.0:0", "0.0.0.0:0"));
+        assert!(!conflict("127.0.0.1:0", "127.0.0.1:0"));
+    }
+
+    #[test]
+    fn test_backup_service_address() {
+        // Loopback addresses are always allowed
+        check_backup_service_address("127.0.0.1:6186".parse().unwrap(), false).unwrap();
+        check_backup_service_address("[::1]:6186".parse().unwrap(), false).unwrap();
+
+        // Public addresses require an explicit override
+        check_backup_service_address("0.0.0.0:6186".parse().unwrap(), false).unwrap_err();
+        check_backup_service_address("10.0.0.1:6186".parse().unwrap(), false).unwrap_err();
+        check_backup_service_address("0.0.0.0:6186".parse().unwrap(), true).unwrap();
+    }
+
+    #[test]
+    fn test_default_validator_config_has_no_conflicts() {
+        check_listen_address_conflicts(
+            &NodeConfig::default_for_validator(),
+            &NodeOptions::default(),
+        )
+        .unwrap();
+    }
+
+    /// Returns a valid config whose storage directory lives in the given temp dir
+    fn valid_node_config(storage_dir: &TempPath) -> NodeConfig {
+        let mut node_config = NodeConfig::default_for_validator();
+        node_config.base.waypoint = WaypointConfig::FromConfig(Waypoint::default());
+        node_config.execution.genesis_file_location = PathBuf::new();
+        node_config.storage.dir = storage_dir.path().to_path_buf();
+        node_config
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let storage_dir = TempPath::new();
+        let report =
+            validate_config(&valid_node_config(&storage_dir), &NodeOptions::default()).unwrap();
+        assert_eq!(report.warnings().len(), 1);
+        assert!(report.warnings()[0].contains("No genesis transaction"));
+    }
+
+    #[test]
+    fn test_validate_config_genesis_not_loaded() {
+        let storage_dir = TempPath::new();
+        let mut node_config = valid_node_config(&storage_dir);
+        node_config.execution.genesis_file_location = "missing_genesis.blob".into();
+        let error = validate_config(&node_config, &NodeOptions::default()).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("missing_genesis.blob"));
+    }
+
+    #[test]
+    fn test_validate_config_missing_waypoint() {
+        let storage_dir = TempPath::new();
+        let mut node_config = valid_node_config(&storage_dir);
+        node_config.base.waypoint = WaypointConfig::None;
+        let error = validate_config(&node_config, &NodeOptions::default()).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("No waypoint"));
+    }
+
+    /// Returns the config of a validator built with a test genesis, which reads its waypoints
+    /// from secure storage in the given config dir
+    fn validator_builder_config(config_dir: &TempPath) -> NodeConfig {
+        config_dir.create_as_dir().unwrap();
+        let (_, _, _, mut validators) = ValidatorBuilder::new(
+            config_dir.path(),
+            cached_framework_packages::module_blobs().to_vec(),
+        )
+        .build(StdRng::from_seed([0; 32]))
+        .unwrap();
+        validators.remove(0).config
+    }
+
+    #[test]
+    fn test_validate_config_waypoint_from_storage() {
+        let config_dir = TempPath::new();
+        let node_config = validator_builder_config(&config_dir);
+        assert!(matches!(
+            node_config.base.waypoint,
+            WaypointConfig::FromStorage(_)
+        ));
+        validate_config(&node_config, &NodeOptions::default()).unwrap();
+    }
+
+    #[test]
+    fn test_validate_config_genesis_waypoint_mismatch() {
+        let config_dir = TempPath::new();
+        let mut node_config = validator_builder_config(&config_dir);
+        node_config.base.waypoint = WaypointConfig::FromConfig(Waypoint::default());
+        let error = validate_config(&node_config, &NodeOptions::default()).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("doesn't match the genesis transaction"));
+    }
+
+    #[test]
+    fn test_validate_config_duplicate_networks() {
+        let storage_dir = TempPath::new();
+        let mut node_config = valid_node_config(&storage_dir);
+        node_config.full_node_networks = vec![
+            NetworkConfig::network_with_id(NetworkId::Public),
+            NetworkConfig::network_with_id(NetworkId::Public),
+        ];
+        let error = validate_config(&node_config, &NodeOptions::default()).unwrap_err();
+        assert!(error
+            .problems()
+            .iter()
+            .any(|problem| problem.contains("Duplicate NetworkId")));
+    }
+
+    #[test]
+    fn test_validate_config_unresolvable_address() {
+        let storage_dir = TempPath::new();
+        let mut node_config = valid_node_config(&storage_dir);
+        node_config.debug_interface.address = "[::1".into();
+        let error = validate_config(&node_config, &NodeOptions::default()).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("debug interface address"));
+    }
+
+    #[test]
+    fn test_validate_config_read_only_storage_dir() {
+        let storage_dir = TempPath::new();
+        storage_dir.create_as_dir().unwrap();
+        let node_config = valid_node_config(&storage_dir);
+        let mut permissions = std::fs::metadata(storage_dir.path()).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(storage_dir.path(), permissions.clone()).unwrap();
+
+        // Privileged users (e.g., root) can write to read-only directories, in which case
+        // the directory must be reported as writable
+        let writable = probe_dir_writable(storage_dir.path()).is_ok();
+        let result = validate_config(&node_config, &NodeOptions::default());
+        permissions.set_readonly(false);
+        std::fs::set_permissions(storage_dir.path(), permissions).unwrap();
+
+        if writable {
+            result.unwrap();
+        } else {
+            let error = result.unwrap_err();
+            assert_eq!(error.problems().len(), 1);
+            assert!(error.problems()[0].contains("isn't writable"));
+        }
+        assert_eq!(std::fs::read_dir(storage_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_validate_config_storage_dir_under_file() {
+        // The storage directory can't be created under a regular file
+        let file = TempPath::new();
+        file.create_as_file().unwrap();
+        let mut node_config = valid_node_config(&file);
+        node_config.storage.dir = file.path().join("db");
+
+        let error = validate_config(&node_config, &NodeOptions::default()).unwrap_err();
+        assert_eq!(error.problems().len(), 1);
+        assert!(error.problems()[0].contains("isn't writable"));
+    }
+
+    #[test]
+    fn test_api_and_network_conflict() {
+        let mut node_config = NodeConfig::default_for_validator();
+        let network_address = node_config
+            .validator_network
+            .as_ref()
+            .unwrap()
+            .listen_address
+            .clone();
+        node_config.api.address = network_address.to_socket_addrs().unwrap().next().unwrap();
+
+        let error =
+            check_listen_address_conflicts(&node_config, &NodeOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("API address"));
+
+        // A disabled API doesn't bind its address
+        let options = NodeOptions {
+            disable_api: true,
+            ..NodeOptions::default()
+        };
+        check_listen_address_conflicts(&node_config, &options).unwrap();
+    }
+
+    #[test]
+    fn test_effective_debug_interface_and_metrics_addresses() {
+        let mut node_config = NodeConfig::default_for_validator();
+        let debug_interface = &mut node_config.debug_interface;
+        debug_interface.address = "127.0.0.1".into();
+        debug_interface.public_metrics_server_port = debug_interface.metrics_server_port;
+        let metrics_address: SocketAddr =
+            format!("127.0.0.1:{}", debug_interface.metrics_server_port)
+                .parse()
+                .unwrap();
+
+        // The public metrics server conflicts with the metrics server, unless it's disabled
+        let error =
+            check_listen_address_conflicts(&node_config, &NodeOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("public metrics server"));
+        let options = NodeOptions {
+            disable_public_metrics_server: true,
+            ..NodeOptions::default()
+        };
+        check_listen_address_conflicts(&node_config, &options).unwrap();
+
+        // An overridden debug interface address replaces the configured one
+        let options = NodeOptions {
+            disable_public_metrics_server: true,
+            debug_interface_address: Some(metrics_address),
+            ..NodeOptions::default()
+        };
+        let error = check_listen_address_conflicts(&node_config, &options).unwrap_err();
+        assert!(error.to_string().contains("debug interface address"));
+        let options = NodeOptions {
+            disable_public_metrics_server: true,
+            disable_metrics_server: true,
+            debug_interface_address: Some(metrics_address),
+            ..NodeOptions::default()
+        };
+        check_listen_address_conflicts(&node_config, &options).unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::metrics;
+use aptos_logger::prelude::*;
+use aptos_types::{account_address::AccountAddress, on_chain_config::ValidatorSet};
+use event_notifications::ReconfigNotificationListener;
+use futures::stream::StreamExt;
+
+/// Checks whether the given validator account is in the on-chain validator set every time
+/// a reconfiguration notification is received (the first of which is delivered once state
+/// sync has initialized). A validator that isn't in the set only produces a warning, as it
+/// may be about to join.
+pub async fn monitor_validator_set_membership(
+    account_address: AccountAddress,
+    mut reconfig_listener: ReconfigNotificationListener,
+) {
+    while let Some(reconfig_notification) = reconfig_listener.next().await {
+        let epoch = reconfig_notification.on_chain_configs.epoch();
+        let validator_set = match reconfig_notification.on_chain_configs.get::<ValidatorSet>() {
+            Ok(validator_set) => validator_set,
+            Err(error) => {
+                warn!(
+                    epoch = epoch,
+                    "Unable to read the validator set from the on-chain configs: {}", error
+                );
+                continue;
+            }
+        };
+
+        let in_validator_set = validator_set
+            .payload()
+            .iter()
+            .any(|validator_info| validator_info.account_address() == &account_address);
+        metrics::IN_VALIDATOR_SET.set(in_validator_set as i64);
+        if in_validator_set {
+            info!(
+                epoch = epoch,
+                account_address = account_address,
+                "This node is in the validator set"
+            );
+        } else {
+            warn!(
+                epoch = epoch,
+                account_address = account_address,
+                "This node is configured as a validator, but it is NOT in the on-chain validator set! \
+                It will not participate in consensus until it joins the set."
+            );
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(unix)]
+
+use aptos_temppath::TempPath;
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use std::{
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+// The maximum time the node is given to start up, and then to shut down
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Polls `condition` until it holds, failing the test if `timeout` elapses first
+fn wait_until(timeout: Duration, description: &str, mut condition: impl FnMut() -> bool) {
+    let start_time = Instant::now();
+    while !condition() {
+        assert!(
+            start_time.elapsed() < timeout,
+            "Timed out waiting for {}",
+            description
+        );
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn wait_for_exit(node: &mut Child, timeout: Duration) -> ExitStatus {
+    let mut exit_status = None;
+    wait_until(timeout, "the node to exit", || {
+        exit_status = node.try_wait().unwrap();
+        exit_status.is_some()
+    });
+    exit_status.unwrap()
+}
+
+#[test]
+fn test_sigterm_shuts_down_cleanly() {
+    let config_dir = TempPath::new();
+    config_dir.create_as_dir().unwrap();
+    let pid_file = config_dir.path().join("aptos-node.pid");
+    let log_file = config_dir.path().join("validator.log");
+
+    let mut node = Command::new(env!("CARGO_BIN_EXE_aptos-node"))
+        .arg("--test")
+        .arg("--random-ports")
+        .arg("--config")
+        .arg(config_dir.path())
+        .arg("--pid-file")
+        .arg(&pid_file)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Wait until the node has fully started, so that every component is torn down
+    wait_until(STARTUP_TIMEOUT, "the node to start", || {
+        std::fs::read_to_string(&log_file)
+            .map(|log| log.contains("Node started"))
+            .unwrap_or(false)
+    });
+    assert!(pid_file.exists());
+
+    kill(Pid::from_raw(node.id() as i32), Signal::SIGTERM).unwrap();
+    let exit_status = wait_for_exit(&mut node, SHUTDOWN_TIMEOUT);
+    assert!(exit_status.success(), "Unclean exit: {}", exit_status);
+    assert!(!pid_file.exists());
+}